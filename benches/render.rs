@@ -0,0 +1,155 @@
+//! Rendering benchmarks over generated token streams.
+//!
+//! Run with `cargo bench --features proc-macro2`.
+
+use std::fmt::Write;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use proc_macro2::TokenStream;
+use proc_macro_faithful_display::{
+    compact_display, faithful_display, faithful_to_string, into_faithful_string, FaithfulLayout,
+    FaithfulOptions,
+};
+
+/// Numbers of functions of the generated sources.
+const SIZES: [usize; 3] = [10, 100, 1000];
+
+/// Nesting depths of the generated nested sources.
+const DEPTHS: [usize; 3] = [8, 32, 128];
+
+/// Generate a shader-like source made of `functions` functions of nested blocks, deeply indented
+/// as DSL blocks embedded in Rust modules are.
+fn source(functions: usize) -> String {
+    let mut src = String::new();
+
+    for i in 0..functions {
+        let _ = write!(
+            src,
+            r#"
+            // function {i}
+            vec4 function_{i}(vec2 uv, float t) {{
+                vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+
+                for (int j = 0; j < {i}; ++j) {{
+                    if (uv.x > float(j) * 0.5) {{
+                        color.rgb += texture(tex, uv + vec2(t, -t)).rgb * 0.25;
+                    }} else {{
+                        color.a   *= 0.5;
+                    }}
+                }}
+
+                return color;
+            }}
+"#
+        );
+    }
+
+    src
+}
+
+/// Generate a source of `depth` nested groups, each holding a few tokens around the next one.
+fn nested_source(depth: usize) -> String {
+    let mut src = String::new();
+
+    for i in 0..depth {
+        let _ = write!(src, "{:indent$}f{i}(a, {{ b[", "", indent = i % 16);
+        src.push('\n');
+    }
+    for i in (0..depth).rev() {
+        let _ = write!(src, "{:indent$}] }}, c)", "", indent = i % 16);
+        src.push('\n');
+    }
+
+    src
+}
+
+fn stream(functions: usize) -> (String, TokenStream) {
+    let src = source(functions);
+    let stream = src.parse().expect("generated source lexes");
+    (src, stream)
+}
+
+fn bench_faithful(c: &mut Criterion) {
+    let mut group = c.benchmark_group("faithful");
+
+    for functions in SIZES {
+        let (src, stream) = stream(functions);
+        group.throughput(Throughput::Bytes(src.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("display", functions),
+            &stream,
+            |b, stream| b.iter(|| faithful_display(stream).to_string()),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("to_string", functions),
+            &stream,
+            |b, stream| b.iter(|| faithful_to_string(stream)),
+        );
+
+        let layout = FaithfulLayout::compute(&stream);
+        group.bench_with_input(
+            BenchmarkId::new("layout", functions),
+            &layout,
+            |b, layout| b.iter(|| layout.to_string()),
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_options(c: &mut Criterion) {
+    let mut group = c.benchmark_group("options");
+    let options = [
+        ("dedent", FaithfulOptions::new().dedent(true)),
+        ("reindent", FaithfulOptions::new().reindent(8)),
+        ("comments", FaithfulOptions::new().comments(true)),
+    ];
+
+    for functions in SIZES {
+        let (src, stream) = stream(functions);
+        group.throughput(Throughput::Bytes(src.len() as u64));
+
+        for (name, options) in &options {
+            group.bench_with_input(BenchmarkId::new(*name, functions), &stream, |b, stream| {
+                b.iter(|| options.string(stream))
+            });
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("compact", functions),
+            &stream,
+            |b, stream| b.iter(|| compact_display(stream).to_string()),
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_nested(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nested");
+
+    for depth in DEPTHS {
+        let src = nested_source(depth);
+        let stream: TokenStream = src.parse().expect("generated source lexes");
+        group.throughput(Throughput::Bytes(src.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("borrowed", depth), &stream, |b, stream| {
+            b.iter(|| faithful_to_string(stream))
+        });
+
+        group.bench_with_input(BenchmarkId::new("owned", depth), &stream, |b, stream| {
+            b.iter_batched(
+                || stream.clone(),
+                into_faithful_string,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_faithful, bench_options, bench_nested);
+criterion_main!(benches);
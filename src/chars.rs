@@ -0,0 +1,179 @@
+//! Characters of a rendering, each with the span it comes from, for custom lexers.
+
+use crate::source_map::{Mapping, Narrowed, SourceMap};
+use crate::token::{SourceSpan, Token};
+use crate::LineColumn;
+
+/// A character of a rendering, and where it comes from.
+#[derive(Clone, Debug)]
+pub struct FaithfulChar<S> {
+    /// The character.
+    pub ch: char,
+    /// Byte offset of the character in the rendering.
+    pub offset: usize,
+    /// Where the character comes from.
+    pub origin: CharOrigin<S>,
+}
+
+/// Where a character of a rendering comes from.
+#[derive(Clone, Debug)]
+pub enum CharOrigin<S> {
+    /// A character of a token – a delimiter or a doc comment included –, with the span of the
+    /// token narrowed down to the character.
+    ///
+    /// As with [`SourceMap::subspan_at`], the span itself is only narrowed inside literals – if
+    /// the backend supports it –, but the positions always are.
+    Token(Narrowed<S>),
+    /// A whitespace – or a preserved comment – emitted between two tokens.
+    Gap {
+        /// Span of the token before the gap, if any.
+        prev: Option<S>,
+        /// Span of the token after the gap, if any.
+        next: Option<S>,
+    },
+}
+
+impl<S> FaithfulChar<S> {
+    /// Span of the token the character belongs to, narrowed down to the character if possible,
+    /// or `None` if the character is in a gap.
+    pub fn span(&self) -> Option<&S> {
+        match &self.origin {
+            CharOrigin::Token(narrowed) => Some(&narrowed.span),
+            CharOrigin::Gap { .. } => None,
+        }
+    }
+}
+
+/// Iterator over the characters of a faithful rendering, each with the span it comes from.
+///
+/// Every character is yielded, whitespaces included: the characters of tokens are attributed to
+/// their token, and the characters emitted between two tokens to the gap between them. This lets
+/// a hand-written lexer for another language attach exact spans to its own tokens.
+///
+/// This is the iterator returned by [`faithful_chars`] and [`FaithfulOptions::chars`].
+///
+/// [`faithful_chars`]: crate::faithful_chars
+/// [`FaithfulOptions::chars`]: crate::FaithfulOptions::chars
+#[derive(Clone, Debug)]
+pub struct FaithfulChars<T>
+where
+    T: Token,
+{
+    output: String,
+    map: SourceMap<T>,
+    /// Byte offset of the next character.
+    offset: usize,
+    /// Index of the first mapping not ending before the next character.
+    index: usize,
+    /// Position in the input of the next character, if it’s in a token.
+    pos: Option<LineColumn>,
+}
+
+impl<T> FaithfulChars<T>
+where
+    T: Token,
+{
+    /// Iterate over the characters of `output`, rendered with the `map` source map.
+    pub(crate) fn new(output: String, map: SourceMap<T>) -> Self {
+        FaithfulChars {
+            output,
+            map,
+            offset: 0,
+            index: 0,
+            pos: None,
+        }
+    }
+
+    /// The whole rendering.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The part of the rendering not yielded yet.
+    pub fn as_str(&self) -> &str {
+        &self.output[self.offset..]
+    }
+
+    /// Source map of the rendering.
+    pub fn source_map(&self) -> &SourceMap<T> {
+        &self.map
+    }
+
+    /// Take the rendering and its source map out of the iterator.
+    pub fn into_parts(self) -> (String, SourceMap<T>) {
+        (self.output, self.map)
+    }
+}
+
+impl<T> Iterator for FaithfulChars<T>
+where
+    T: Token,
+{
+    type Item = FaithfulChar<T::Span>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let ch = self.output[offset..].chars().next()?;
+        self.offset += ch.len_utf8();
+
+        let mappings = self.map.mappings();
+        while mappings
+            .get(self.index)
+            .is_some_and(|mapping| mapping.range.end <= offset)
+        {
+            self.index += 1;
+            self.pos = None;
+        }
+
+        let origin = match mappings.get(self.index) {
+            Some(mapping) if mapping.range.start <= offset => {
+                token_char(mapping, &mut self.pos, ch, offset)
+            }
+
+            next => CharOrigin::Gap {
+                prev: self.index.checked_sub(1).map(|i| mappings[i].span.clone()),
+                next: next.map(|mapping| mapping.span.clone()),
+            },
+        };
+
+        Some(FaithfulChar { ch, offset, origin })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.output.len() - self.offset;
+        (remaining.div_ceil(4), Some(remaining))
+    }
+}
+
+/// Origin of the character at `offset`, in the token of `mapping`; `pos` is the position in the
+/// input of the character, if known, and is moved past it.
+fn token_char<T>(
+    mapping: &Mapping<T>,
+    pos: &mut Option<LineColumn>,
+    ch: char,
+    offset: usize,
+) -> CharOrigin<T::Span>
+where
+    T: Token,
+{
+    let start = pos.unwrap_or_else(|| mapping.span.start());
+    let end = if ch == '\n' {
+        LineColumn::new(start.line + 1, 0)
+    } else {
+        LineColumn::new(start.line, start.column + 1)
+    };
+    *pos = Some(end);
+
+    let local = offset - mapping.range.start;
+    let narrowed = mapping
+        .literal
+        .as_ref()
+        .and_then(|literal| literal.subspan(local..local + ch.len_utf8()));
+
+    CharOrigin::Token(Narrowed {
+        exact: narrowed.is_some(),
+        span: narrowed.unwrap_or_else(|| mapping.span.clone()),
+        start,
+        end,
+    })
+}
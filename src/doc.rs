@@ -0,0 +1,104 @@
+//! Doc comments, which reach procedural macros as `#[doc = "…"]` attributes.
+
+use crate::token::{Delimiter, SourceSpan, Token, TokenKind};
+
+/// Whether `group` has the span of the doc comment starting with a `#` which span is `hash`.
+///
+/// Doc comments are recognized by their shape: the `#` and the `[doc = "…"]` group both have the
+/// span of the comment, which cannot happen with a hand-written attribute.
+pub(crate) fn has_comment_span<T>(hash: &T::Span, group: &T) -> bool
+where
+    T: Token,
+{
+    let span = group.span();
+    span.start() == hash.start() && span.end() == hash.end()
+}
+
+/// Text of the doc comment made of a `#`, an optional `!` and a group delimited by `delimiter`
+/// starting with the `head` tokens, if they are one.
+///
+/// The group must have the span of the comment – see [`has_comment_span`]; at least four tokens
+/// of the group, if any, must be in `head` so that extra tokens can be detected.
+pub(crate) fn doc_comment<T>(inner: bool, delimiter: Delimiter, head: &[T]) -> Option<String>
+where
+    T: Token,
+{
+    let [doc, eq, lit] = head else {
+        return None;
+    };
+
+    if delimiter != Delimiter::Bracket
+        || !matches!(doc.kind(), TokenKind::Ident)
+        || doc.to_string() != "doc"
+        || !matches!(eq.kind(), TokenKind::Punct('=', _))
+        || !matches!(lit.kind(), TokenKind::Literal)
+    {
+        return None;
+    }
+
+    // prefer the comment as written, if available
+    if let Some(text) = lit.span().source_text() {
+        if text.starts_with("//") || text.starts_with("/*") {
+            return Some(text);
+        }
+    }
+
+    let value = unescape(&lit.to_string())?;
+    let text = match (inner, value.contains('\n')) {
+        (false, false) => format!("///{}", value),
+        (true, false) => format!("//!{}", value),
+        (false, true) => format!("/**{}*/", value),
+        (true, true) => format!("/*!{}*/", value),
+    };
+
+    Some(text)
+}
+
+/// Value of a string literal, raw or not.
+fn unescape(lit: &str) -> Option<String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = raw.get(hashes..raw.len().checked_sub(hashes)?)?;
+        return raw.strip_prefix('"')?.strip_suffix('"').map(str::to_owned);
+    }
+
+    let cooked = lit.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(cooked.len());
+    let mut chars = cooked.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'x' => {
+                let hex = chars.as_str().get(..2)?;
+                value.push(char::from(u8::from_str_radix(hex, 16).ok()?));
+                chars.nth(1);
+            }
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let end = rest.find('}')?;
+                let code = u32::from_str_radix(&rest[..end].replace('_', ""), 16).ok()?;
+                value.push(char::from_u32(code)?);
+                chars = rest[end + 1..].chars();
+            }
+            '\n' => {
+                // line continuation: skip the leading whitespaces of the next line
+                chars = chars.as_str().trim_start().chars();
+            }
+            _ => return None,
+        }
+    }
+
+    Some(value)
+}
@@ -0,0 +1,67 @@
+//! Errors reported when a token stream cannot be displayed faithfully.
+
+use std::error::Error;
+use std::fmt;
+
+/// Reason why a token stream could not be displayed faithfully.
+///
+/// Every variant but [`FaithfulError::Fmt`] carries the span of the offending token, so that a
+/// procedural macro can report the error at the right location, for instance by turning it into
+/// a `compile_error!` invocation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum FaithfulError<S> {
+    /// A token starts before the end of the previous one, or has an empty span.
+    BackwardsSpan(S),
+    /// A token comes from a different source file than the first token.
+    DifferentFile(S),
+    /// A token has the call-site span of the macro invocation instead of a span of the input.
+    CallSite(S),
+    /// A group has no span for its delimiters.
+    MissingDelimiterSpan(S),
+    /// A group is nested deeper than the maximum depth.
+    TooDeep(S),
+    /// The output could not be written.
+    Fmt,
+}
+
+impl<S> FaithfulError<S> {
+    /// Span of the offending token, if any.
+    pub fn span(&self) -> Option<&S> {
+        match self {
+            FaithfulError::BackwardsSpan(span)
+            | FaithfulError::DifferentFile(span)
+            | FaithfulError::CallSite(span)
+            | FaithfulError::MissingDelimiterSpan(span)
+            | FaithfulError::TooDeep(span) => Some(span),
+            FaithfulError::Fmt => None,
+        }
+    }
+}
+
+impl<S> From<fmt::Error> for FaithfulError<S> {
+    fn from(_: fmt::Error) -> Self {
+        FaithfulError::Fmt
+    }
+}
+
+impl<S> fmt::Display for FaithfulError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaithfulError::BackwardsSpan(_) => {
+                f.write_str("token starts before the end of the previous one")
+            }
+            FaithfulError::DifferentFile(_) => {
+                f.write_str("token comes from a different source file than the first token")
+            }
+            FaithfulError::CallSite(_) => f.write_str("token has a synthetic call-site span"),
+            FaithfulError::MissingDelimiterSpan(_) => {
+                f.write_str("group has no span for its delimiters")
+            }
+            FaithfulError::TooDeep(_) => f.write_str("group is nested too deeply"),
+            FaithfulError::Fmt => f.write_str("cannot write the output"),
+        }
+    }
+}
+
+impl<S> Error for FaithfulError<S> where S: fmt::Debug {}
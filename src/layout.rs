@@ -0,0 +1,245 @@
+//! Flattened layouts of token streams, computed once and rendered as many times as needed.
+
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use crate::options::FaithfulOptions;
+use crate::render::{first_known_start, min_start_column};
+use crate::strategy::Lexeme;
+use crate::token::{SourceSpan, Token, TokenKind};
+use crate::{FaithfulDisplay, LineColumn, Region};
+
+/// A token – a delimiter or a doc comment included – of a [`FaithfulLayout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutEntry {
+    /// Textual form of the token, as displayed.
+    pub text: String,
+    /// Position at which the token starts in the input.
+    pub start: LineColumn,
+    /// Position at which the token ends in the input.
+    pub end: LineColumn,
+    /// What the token is.
+    pub kind: Lexeme,
+    /// Number of visible groups enclosing the token.
+    ///
+    /// The delimiters of a group are not enclosed by the group itself.
+    pub depth: usize,
+}
+
+impl LayoutEntry {
+    /// Region the token covers in the input.
+    pub fn region(&self) -> Region {
+        Region::new(self.start, self.end)
+    }
+}
+
+impl Display for LayoutEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A token stream flattened into a sequence of [`LayoutEntry`]s, in display order.
+///
+/// Displaying a token stream walks – and clones – its token tree every time. A layout is computed
+/// once, with [`FaithfulLayout::compute`], and owns everything needed to display the tokens
+/// afterwards: it can be displayed many times, with any [`FaithfulOptions`], queried, sliced or –
+/// with the `serde` feature – serialized, without touching the token stream again.
+///
+/// Invisible groups are flattened away, and doc comments are single entries. Comments the source
+/// contains are not part of a layout, so [`FaithfulOptions::comments`] has no effect on it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FaithfulLayout {
+    entries: Vec<LayoutEntry>,
+}
+
+impl FaithfulLayout {
+    /// Flatten a token stream into a layout.
+    pub fn compute<T>(stream: &T) -> Self
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        let (output, map) = FaithfulOptions::default().string_with_map(stream);
+
+        let entries = map
+            .mappings()
+            .iter()
+            .map(|mapping| LayoutEntry {
+                text: output[mapping.range.clone()].to_owned(),
+                start: mapping.span.start(),
+                end: mapping.span.end(),
+                kind: mapping.lexeme,
+                depth: mapping.depth,
+            })
+            .collect();
+
+        FaithfulLayout { entries }
+    }
+
+    /// All the entries, in display order.
+    pub fn entries(&self) -> &[LayoutEntry] {
+        &self.entries
+    }
+
+    /// Take the entries out of the layout.
+    pub fn into_entries(self) -> Vec<LayoutEntry> {
+        self.entries
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the layout has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entry which span contains `pos`, if any.
+    pub fn entry_at(&self, pos: LineColumn) -> Option<&LayoutEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.region().contains(pos))
+    }
+
+    /// Region covered by the entries, if any.
+    pub fn region(&self) -> Option<Region> {
+        self.entries
+            .iter()
+            .map(LayoutEntry::region)
+            .reduce(Region::cover)
+    }
+
+    /// Layout made of the entries entirely within `region` in the input.
+    pub fn within(&self, region: Region) -> FaithfulLayout {
+        self.entries
+            .iter()
+            .filter(|entry| region.contains_region(entry.region()))
+            .cloned()
+            .collect()
+    }
+
+    /// Layout made of the entries in `range`.
+    ///
+    /// The range can split groups: their remaining delimiters are displayed as any other token.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> FaithfulLayout {
+        self.entries[range].iter().cloned().collect()
+    }
+
+    /// Layout made of the entries starting on `lines` in the input.
+    pub fn lines(&self, lines: Range<usize>) -> FaithfulLayout {
+        self.entries
+            .iter()
+            .filter(|entry| lines.contains(&entry.start.line))
+            .cloned()
+            .collect()
+    }
+
+    /// Create a [`Display`] object out of the layout with `options`.
+    ///
+    /// Errors are reported as with [`FaithfulOptions::display`].
+    pub fn display_with(&self, options: impl Into<FaithfulOptions>) -> DisplayedLayout<'_> {
+        DisplayedLayout {
+            layout: self,
+            options: options.into(),
+        }
+    }
+}
+
+impl From<Vec<LayoutEntry>> for FaithfulLayout {
+    fn from(entries: Vec<LayoutEntry>) -> Self {
+        FaithfulLayout { entries }
+    }
+}
+
+impl FromIterator<LayoutEntry> for FaithfulLayout {
+    fn from_iter<I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = LayoutEntry>,
+    {
+        FaithfulLayout {
+            entries: entries.into_iter().collect(),
+        }
+    }
+}
+
+impl Display for FaithfulLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display_with(FaithfulOptions::default()).fmt(f)
+    }
+}
+
+/// A [`FaithfulLayout`] displayed with [`FaithfulOptions`].
+///
+/// This is the [`Display`] object returned by [`FaithfulLayout::display_with`].
+#[derive(Clone, Debug)]
+pub struct DisplayedLayout<'a> {
+    layout: &'a FaithfulLayout,
+    options: FaithfulOptions,
+}
+
+impl DisplayedLayout<'_> {
+    /// Options the layout is displayed with.
+    pub fn options(&self) -> &FaithfulOptions {
+        &self.options
+    }
+}
+
+impl Display for DisplayedLayout<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries = &self.layout.entries;
+        let spans = || entries.iter().map(|entry| (entry.start, entry.end, false));
+        let first = first_known_start(spans());
+        let min_indent = || min_start_column(spans());
+
+        if let Some(mut renderer) = self.options.renderer::<_, Entry>(f, first, min_indent) {
+            let tokens = entries
+                .iter()
+                .map(|entry| (entry.text.as_str(), entry.region(), entry.kind));
+
+            renderer
+                .flat(tokens)
+                .and_then(|()| renderer.finish())
+                .map_err(|_| fmt::Error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`LayoutEntry`] as a [`Token`], so that layouts can be rendered.
+///
+/// Delimiters and doc comments are opaque tokens, as literals are.
+#[derive(Clone, Copy, Debug)]
+struct Entry<'a>(&'a LayoutEntry);
+
+impl Display for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Token for Entry<'_> {
+    type Span = Region;
+    type Stream = std::iter::Empty<Self>;
+
+    fn span(&self) -> Region {
+        self.0.region()
+    }
+
+    fn kind(&self) -> TokenKind<Region, Self::Stream> {
+        match self.0.kind {
+            Lexeme::Ident => TokenKind::Ident,
+            Lexeme::Punct { ch, spacing, .. } => TokenKind::Punct(ch, spacing),
+            _ => TokenKind::Literal,
+        }
+    }
+}
@@ -1,5 +1,3 @@
-#![feature(proc_macro_span)]
-
 //! An alternative [`Display`] impl for [proc_macro], respecting the input layout and formatting.
 //!
 //! The idea is that the impl of [`Display`] for [proc_macro] types doesn’t respect the input’s
@@ -11,201 +9,384 @@
 //! This crate provides an implementation of [`Display`] that respects the input’s formatting, so
 //! that one can display a [`TokenStream`] and parse it with a more esoteric parser than [syn].
 //!
-//! > Currently, this crate highly depends on *nightly* features. You cannot use it on the *stable*
-//! > channel… just yet.
-//!
 //! You can get a faithful [`Display`] object by calling the [`faithful_display`] function on your
 //! [`TokenStream`].
 //!
+//! Doc comments, which reach procedural macros as `#[doc = "…"]` attributes, are displayed back
+//! as the `///`, `//!` or `/** … */` comments they were written as.
+//!
+//! The layout of the input can also be traded for another one – a compact or a pretty one, for
+//! instance – by choosing a [`LayoutStrategy`] with [`FaithfulOptions::layout`].
+//!
+//! A token stream displayed many times can be flattened once into a [`FaithfulLayout`], which owns
+//! everything needed to display it – and to query or serialize its tokens – afterwards.
+//!
+//! Parsers of other languages can also skip the string altogether, and work either on tokens
+//! annotated with the whitespaces preceding them – see [`tokens_with_trivia`] – or on characters
+//! annotated with their spans – see [`faithful_chars`].
+//!
 //! > At the time of writing, traits don’t allow [existential `impl Trait`] to be used in methods.
 //! > This is unfortunate, then the feature is accessed through a function instead of a method.
 //!
+//! # Backends
+//!
+//! [proc_macro] types are always supported. Enabling the `proc-macro2` feature adds support for
+//! the [proc-macro2] types as well, using its `span-locations` feature to get line and column
+//! information. Both backends share the same rendering logic and are displayed with the same
+//! [`faithful_display`] function.
+//!
+//! Some [proc_macro] features – such as narrowing the span of a literal – are only available on
+//! the *nightly* channel, and are enabled with the `nightly` feature.
+//!
 //! [EDSLs]: https://wiki.haskell.org/Embedded_domain_specific_language
 //! [syn]: https://crates.io/crates/syn
+//! [`Display`]: std::fmt::Display
+//! [`TokenStream`]: proc_macro::TokenStream
+//! [`LayoutStrategy`]: strategy::LayoutStrategy
+//! [proc-macro2]: https://crates.io/crates/proc-macro2
 //! [existential `impl Trait`]: https://rust-lang-nursery.github.io/edition-guide/rust-2018/trait-system/impl-trait-for-returning-complex-types-with-ease.html#return-position
 
+#![cfg_attr(feature = "nightly", feature(proc_macro_span))]
+
 extern crate proc_macro;
 
-use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Span, TokenStream, TokenTree};
-use std::fmt::{self, Display, Write};
-use std::iter::FromIterator;
+/// Implement [`FaithfulDisplay`] for the token stream and token types of a backend, given that its
+/// `TokenTree` implements [`Token`].
+macro_rules! impl_faithful_display {
+    ($backend:ident) => {
+        impl $crate::FaithfulDisplay for $backend::TokenStream {
+            type Token = $backend::TokenTree;
+
+            fn faithful_tokens(&self) -> impl Iterator<Item = Self::Token> {
+                self.clone().into_iter()
+            }
+
+            fn into_faithful_tokens(self) -> impl Iterator<Item = Self::Token> {
+                self.into_iter()
+            }
+        }
+
+        impl_faithful_display!($backend, Group, Ident, Literal, Punct);
+    };
+
+    ($backend:ident, $($token:ident),*) => {
+        $(
+            impl $crate::FaithfulDisplay for $backend::$token {
+                type Token = $backend::TokenTree;
+
+                fn faithful_tokens(&self) -> impl Iterator<Item = Self::Token> {
+                    std::iter::once(self.clone().into())
+                }
+
+                fn into_faithful_tokens(self) -> impl Iterator<Item = Self::Token> {
+                    std::iter::once(self.into())
+                }
+            }
+        )*
+    };
+}
+
+mod chars;
+mod doc;
+mod error;
+mod layout;
+mod options;
+mod pm;
+#[cfg(feature = "proc-macro2")]
+mod pm2;
+mod position;
+mod render;
+mod source;
+mod source_map;
+pub mod strategy;
+pub mod token;
+mod trivia;
+mod write;
+
+use std::fmt;
+use std::io;
+
+pub use chars::{CharOrigin, FaithfulChar, FaithfulChars};
+pub use error::FaithfulError;
+pub use layout::{DisplayedLayout, FaithfulLayout, LayoutEntry};
+pub use options::{
+    Displayed, DisplayedOwned, FaithfulOptions, FirstLine, Indent, Newline, SpanFallback,
+};
+pub use position::{Distance, LineColumn, Position, Region};
+use render::Renderer;
+pub use source_map::{Mapping, Narrowed, SourceMap};
+use token::Token;
+pub use trivia::{Trivia, TriviaTokens};
 
 /// A more faithful [`Display`].
 ///
-/// This trait works by accumulating a [`Span`] as it formats tokens. By recomputing on the
+/// This trait works by accumulating a [`LineColumn`] as it formats tokens. By recomputing on the
 /// fly on the layout of each token, it’s possible to insert newlines and spaces to respect the
 /// initial formatting.
+///
+/// It is implemented for every [`Token`], slices of [`Token`]s, and the token stream and token
+/// types of each backend.
+///
+/// [`Display`]: std::fmt::Display
 pub trait FaithfulDisplay {
-    /// Display a token in a faithful way.
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error>;
-}
+    /// Type of the tokens to display.
+    type Token: Token;
 
-impl FaithfulDisplay for Ident {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        let current_span = self.span();
-        whitespace_adjust_span(f, prev_span, current_span.start())?;
+    /// Tokens to display, in order.
+    fn faithful_tokens(&self) -> impl Iterator<Item = Self::Token>;
 
-        self.fmt(f).map(|_| current_span.end())
+    /// Tokens to display, in order, taking ownership of the tokens rather than cloning them.
+    ///
+    /// Implementors owning their tokens should override this method; by default, the tokens are
+    /// cloned.
+    fn into_faithful_tokens(self) -> impl Iterator<Item = Self::Token>
+    where
+        Self: Sized,
+    {
+        self.faithful_tokens().collect::<Vec<_>>().into_iter()
     }
-}
 
-impl FaithfulDisplay for Literal {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        let current_span = self.span();
-        whitespace_adjust_span(f, prev_span, current_span.start())?;
+    /// Position at which the first token starts, if any.
+    ///
+    /// Invisible groups are transparent: the first token inside them is looked for instead.
+    fn faithful_start(&self) -> Option<LineColumn> {
+        render::first_start::<Self::Token>(self.faithful_tokens())
+    }
 
-        self.fmt(f).map(|_| current_span.end())
+    /// Region covered by the tokens, if any.
+    ///
+    /// Invisible groups are transparent: only the tokens inside them are covered.
+    fn faithful_region(&self) -> Option<Region> {
+        render::region::<Self::Token>(self.faithful_tokens())
+    }
+
+    /// Display the tokens in a faithful way, starting from the `prev` position, and return the
+    /// position at which the last token ends.
+    fn faithful_fmt(
+        &self,
+        f: &mut fmt::Formatter,
+        prev: LineColumn,
+    ) -> Result<LineColumn, fmt::Error> {
+        let options = FaithfulOptions::default();
+        let mut renderer = Renderer::new(f, prev, &options);
+        renderer
+            .stream(self.faithful_tokens())
+            .map_err(|_| fmt::Error)?;
+        Ok(renderer.position())
     }
 }
 
-impl FaithfulDisplay for Punct {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        let current_span = self.span();
-        whitespace_adjust_span(f, prev_span, current_span.start())?;
+impl<T> FaithfulDisplay for T
+where
+    T: Token,
+{
+    type Token = T;
 
-        f.write_char(self.as_char()).map(|_| current_span.end())
+    fn faithful_tokens(&self) -> impl Iterator<Item = Self::Token> {
+        std::iter::once(self.clone())
+    }
+
+    fn into_faithful_tokens(self) -> impl Iterator<Item = Self::Token> {
+        std::iter::once(self)
     }
 }
 
-impl FaithfulDisplay for Group {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        let current_span = self.span_open();
-        whitespace_adjust_span(f, prev_span, current_span.start())?;
-
-        match self.delimiter() {
-            Delimiter::Parenthesis => {
-                faithful_delimited(
-                    f,
-                    '(',
-                    ')',
-                    self.stream(),
-                    current_span.end(),
-                    self.span_close().start(),
-                )?;
-            }
+impl<T> FaithfulDisplay for [T]
+where
+    T: Token,
+{
+    type Token = T;
 
-            Delimiter::Brace => {
-                faithful_delimited(
-                    f,
-                    '{',
-                    '}',
-                    self.stream(),
-                    current_span.end(),
-                    self.span_close().start(),
-                )?;
-            }
+    fn faithful_tokens(&self) -> impl Iterator<Item = Self::Token> {
+        self.iter().cloned()
+    }
+}
 
-            Delimiter::Bracket => {
-                faithful_delimited(
-                    f,
-                    '[',
-                    ']',
-                    self.stream(),
-                    current_span.end(),
-                    self.span_close().start(),
-                )?;
-            }
+/// Create a [`Display`] object out of a [`TokenStream`] that respects as closely as possible its
+/// formatting.
+///
+/// Any type implementing [`FaithfulDisplay`] is accepted, so that both [proc_macro] and
+/// [proc-macro2] token streams can be displayed. Tokens that start before the end of the previous
+/// one are separated by a single space; see [`faithful_display_with`] to change that.
+///
+/// The output starts with the first token, while the following lines keep the column of their
+/// first token: see [`FirstLine::Anchored`] to indent the first line as well.
+///
+/// > Disclaimer: because this function takes a reference and because [`TokenStream`] – at the time
+/// > of writing – doesn’t support reference-based iteration, the token stream is cloned every time
+/// > it’s displayed, as is the stream of each group; nested streams are thus cloned once per
+/// > nesting level. See [`faithful_display_owned`] to avoid cloning the outer stream.
+///
+/// [`Display`]: std::fmt::Display
+/// [`TokenStream`]: proc_macro::TokenStream
+/// [proc-macro2]: https://crates.io/crates/proc-macro2
+pub fn faithful_display<T>(stream: &T) -> Displayed<'_, T>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::default().display(stream)
+}
 
-            Delimiter::None => {
-                let line_col = self.stream().faithful_fmt(f, current_span.end())?;
-                whitespace_adjust_span(f, prev_span, line_col)?;
-            }
-        }
+/// Create a [`Display`] object out of an owned [`TokenStream`], like [`faithful_display`].
+///
+/// Unlike [`faithful_display`], the token stream is not cloned – only the stream of each group is,
+/// once every time it’s displayed.
+///
+/// [`Display`]: std::fmt::Display
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_display_owned<T>(stream: T) -> DisplayedOwned<T::Token>
+where
+    T: FaithfulDisplay,
+{
+    FaithfulOptions::default().display_owned(stream)
+}
 
-        Ok(self.span_close().end())
-    }
+/// Create a [`Display`] object out of a [`TokenStream`], like [`faithful_display`], with
+/// [`FaithfulOptions`].
+///
+/// A [`SpanFallback`] can be passed directly if that’s the only option to change.
+///
+/// [`Display`]: std::fmt::Display
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_display_with<T>(stream: &T, options: impl Into<FaithfulOptions>) -> Displayed<'_, T>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    options.into().display(stream)
 }
 
-impl FaithfulDisplay for TokenStream {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        let mut current_span = prev_span;
+/// Create a [`Display`] object out of a [`TokenStream`] that emits the shortest text still lexing
+/// to the same tokens.
+///
+/// This is meant for languages in which whitespaces are not significant: tokens are only
+/// separated where they would merge – as two identifiers would – or where a punctuation would
+/// become joint with the next one. The layout of the input is ignored, but doc comments are kept.
+///
+/// This is a shorthand for displaying with the [`Compact`] layout strategy.
+///
+/// [`Display`]: std::fmt::Display
+/// [`TokenStream`]: proc_macro::TokenStream
+/// [`Compact`]: strategy::Compact
+pub fn compact_display<T>(stream: &T) -> Displayed<'_, T>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::new()
+        .layout(strategy::Compact)
+        .display(stream)
+}
 
-        for tree in self.clone() {
-            current_span = tree.faithful_fmt(f, current_span)?;
-        }
+/// Display a [`TokenStream`] faithfully into a [`fmt::Write`], such as a [`String`] or a
+/// [`Formatter`], without going through [`Display`].
+///
+/// [`Display`]: std::fmt::Display
+/// [`Formatter`]: std::fmt::Formatter
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_write_fmt<T, W>(stream: &T, out: &mut W) -> fmt::Result
+where
+    T: FaithfulDisplay + ?Sized,
+    W: fmt::Write,
+{
+    FaithfulOptions::default()
+        .try_write(stream, out)
+        .map_err(|_| fmt::Error)
+}
 
-        Ok(current_span)
-    }
+/// Display a [`TokenStream`] faithfully into an [`io::Write`], such as a file or a hasher,
+/// without buffering the whole output.
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_write_io<T, W>(stream: &T, out: &mut W) -> io::Result<()>
+where
+    T: FaithfulDisplay + ?Sized,
+    W: io::Write,
+{
+    FaithfulOptions::default().write_io(stream, out)
 }
 
-impl FaithfulDisplay for TokenTree {
-    fn faithful_fmt(&self, f: &mut fmt::Formatter, prev_span: Span) -> Result<Span, fmt::Error> {
-        match self {
-            TokenTree::Group(gr) => gr.faithful_fmt(f, prev_span),
-            TokenTree::Ident(ident) => ident.faithful_fmt(f, prev_span),
-            TokenTree::Punct(p) => p.faithful_fmt(f, prev_span),
-            TokenTree::Literal(lit) => lit.faithful_fmt(f, prev_span),
-        }
-    }
+/// Display a [`TokenStream`] faithfully into a [`String`], allocated up-front from the extent of
+/// the spans of the tokens.
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_to_string<T>(stream: &T) -> String
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::default().string(stream)
 }
 
-/// Create a [`Display`] object out of a [`TokenStream`] that respects as closely as possible its
-/// formatting.
+/// Display an owned [`TokenStream`] faithfully into a [`String`], like [`faithful_to_string`],
+/// without cloning it.
 ///
-/// > Disclaimer: because this function takes a reference and because [`TokenStream`] – at the time
-/// > of writing – doesn’t support reference-based iteration, a complete deep clone of the token
-/// > tree has to be performed prior to displaying it.
-pub fn faithful_display(stream: &TokenStream) -> impl Display + '_ {
-    struct D<'a>(&'a TokenStream);
-
-    impl<'a> fmt::Display for D<'a> {
-        fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-            // get the first span, if any
-            let mut iter = self.0.clone().into_iter();
-            let first = iter.next();
-
-            if let Some(tree) = first {
-                let first_line_col = tree.span().start();
-                let line_col = tree.faithful_fmt(f, first_line_col)?;
-
-                TokenStream::from_iter(iter)
-                    .faithful_fmt(f, line_col)
-                    .map(|_| ())
-            } else {
-                Ok(())
-            }
-        }
-    }
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn into_faithful_string<T>(stream: T) -> String
+where
+    T: FaithfulDisplay,
+{
+    FaithfulOptions::default().into_string(stream)
+}
 
-    D(stream)
-}
-
-/// Automatically adjust with whitespaces a formatter based on the current span and the previous
-/// one.
-///
-/// This function is key to the overall implementation, has it enables to respect the input
-/// indentation and general formatting.
-fn whitespace_adjust_span(
-    f: &mut fmt::Formatter,
-    prev_span: Span,
-    current_span: Span,
-) -> Result<(), fmt::Error> {
-    if current_span.line() == prev_span.line() {
-        // we are on the same line, we just have to adjust the number of spaces
-        let nb_spaces = current_span.column() - prev_span.column();
-        f.write_str(" ".repeat(nb_spaces).as_str())
-    } else {
-        // we are on different lines; first add the newlines difference, then adjust with spaces
-        let nb_newlines = current_span.line() - prev_span.line();
-        let nb_spaces = current_span.column();
-        f.write_str("\n".repeat(nb_newlines).as_str())?;
-        f.write_str(" ".repeat(nb_spaces).as_str())
-    }
+/// Display a [`TokenStream`] faithfully into a [`String`], failing if its layout cannot be
+/// respected.
+///
+/// This is the strict version of [`faithful_display`]: instead of falling back to a separator,
+/// a [`FaithfulError`] carrying the span of the offending token is returned.
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn try_faithful_string<T>(
+    stream: &T,
+) -> Result<String, FaithfulError<<T::Token as Token>::Span>>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(stream)
 }
 
-/// Display a token stream that is surrounded by two matching characters.
-fn faithful_delimited(
-    f: &mut fmt::Formatter,
-    del_first: char,
-    del_end: char,
-    stream: TokenStream,
-    prev_span: Span,
-    final_span: Span,
-) -> Result<(), fmt::Error> {
-    f.write_char(del_first)?;
+/// Display a [`TokenStream`] faithfully into a [`String`], along with a [`SourceMap`] mapping
+/// every emitted token back to its span.
+///
+/// This is useful to report errors found by an external parser run on the output at the right
+/// location in the Rust source. Locations can be narrowed down inside tokens with
+/// [`SourceMap::subspan_at`] and [`SourceMap::subspan_for_range`].
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_string_with_map<T>(stream: &T) -> (String, SourceMap<T::Token>)
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::default().string_with_map(stream)
+}
 
-    let current_span = stream.faithful_fmt(f, prev_span)?;
+/// Iterate over the characters of the faithful rendering of a [`TokenStream`], each with the span
+/// it comes from.
+///
+/// This is meant for lexers of other languages, which consume characters rather than Rust tokens
+/// but still need to attach spans to their own tokens. Whitespaces emitted between two tokens are
+/// attributed to the gap between them.
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn faithful_chars<T>(stream: &T) -> FaithfulChars<T::Token>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    FaithfulOptions::default().chars(stream)
+}
 
-    whitespace_adjust_span(f, current_span, final_span)?;
-    f.write_char(del_end)
+/// Iterate over the tokens of a [`TokenStream`], each with the whitespaces preceding it.
+///
+/// This is meant for parsers working on tokens rather than on strings, which need to know whether
+/// `a.b` was written as such or as `a . b`, or whether two statements are on different lines.
+/// Invisible groups are transparent; the tokens inside visible groups are iterated over with
+/// [`TriviaTokens::inside`].
+///
+/// [`TokenStream`]: proc_macro::TokenStream
+pub fn tokens_with_trivia<T>(
+    stream: &T,
+) -> TriviaTokens<impl Iterator<Item = T::Token> + '_, T::Token>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    TriviaTokens::new(stream.faithful_tokens())
 }
@@ -0,0 +1,543 @@
+//! Rendering options.
+
+use std::fmt::{self, Display};
+use std::io;
+use std::sync::Arc;
+
+use crate::chars::FaithfulChars;
+use crate::error::FaithfulError;
+use crate::render::{extent, first_start, min_indent, Renderer};
+use crate::source_map::SourceMap;
+use crate::strategy::LayoutStrategy;
+use crate::token::Token;
+use crate::write::IoWriter;
+use crate::{FaithfulDisplay, LineColumn};
+
+/// What to do when a token starts before the end of the previous one, or has an empty span.
+///
+/// Tokens are not always laid out in order: tokens generated with `quote!`, spanned with
+/// `Span::call_site()` or forwarded by `macro_rules!` can start before – or overlap – the previous
+/// token. Their layout cannot be respected, so a separator is chosen instead. Such tokens don’t
+/// move the rendering position: the tokens following them are laid out relatively to the last
+/// token that was in order – or separated from them as well, if that token is adjacent to them.
+///
+/// The same goes for synthetic tokens, which span is the call-site one or covers the tokens
+/// following them – as the span of a group does, when borrowed by a generated token. They are
+/// never used to decide where the first line starts either.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SpanFallback {
+    /// Separate the tokens with a single space.
+    #[default]
+    Space,
+    /// Don’t separate the tokens.
+    Nothing,
+    /// Separate the tokens with a single space or not depending on their kinds and the
+    /// [`Spacing`] of punctuations: identifiers and literals are always separated, so that they
+    /// don’t merge, but there is no space inside `::` or after a joint punctuation, for instance.
+    ///
+    /// This mixes well generated tokens – which all share a span – with tokens of the input, which
+    /// are still laid out faithfully.
+    ///
+    /// [`Spacing`]: crate::token::Spacing
+    Heuristic,
+    /// Fail to display, reporting a [`FaithfulError`].
+    ///
+    /// Tokens with a call-site span, tokens coming from another source file than the first
+    /// token, and groups without delimiter spans are reported as well.
+    Strict,
+}
+
+/// Style of the newlines emitted between tokens.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Newline {
+    /// `\n`, as on Unix.
+    #[default]
+    Lf,
+    /// `\r\n`, as on Windows.
+    CrLf,
+}
+
+impl Newline {
+    /// The newline itself.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Characters the indentation of lines is made of.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Indent {
+    /// Spaces, one per column.
+    #[default]
+    Spaces,
+    /// Tabs, one per `width` columns – the remaining columns are indented with spaces.
+    ///
+    /// Columns are counted in characters, so a tab of the input counts as one column: use a
+    /// width of 1 to indent tab-indented input with tabs again.
+    Tabs {
+        /// Number of columns a tab stands for.
+        width: usize,
+    },
+}
+
+/// Where the first line of the output starts.
+///
+/// The other lines are always indented to the column of their first token – possibly
+/// [dedented](FaithfulOptions::dedent) or [re-indented](FaithfulOptions::reindent).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FirstLine {
+    /// Start the output with the first token, without leading whitespace.
+    ///
+    /// The first line is then misaligned with the other ones, unless the first token is at the
+    /// smallest column.
+    #[default]
+    Unanchored,
+    /// Indent the first token to its column, as the other lines are, so that all the lines are
+    /// consistent.
+    Anchored,
+}
+
+/// Options of the faithful rendering.
+///
+/// Options are set with the builder methods, then used to display token streams with
+/// [`FaithfulOptions::display`], [`FaithfulOptions::try_string`] or
+/// [`FaithfulOptions::string_with_map`]. The default options are the ones used by
+/// [`faithful_display`].
+///
+/// Whitespace options – [`newline`], [`indent`], [`max_blank_lines`] and
+/// [`trim_trailing_whitespace`] – apply to the whitespaces emitted between tokens, including
+/// preserved comments, but never to the text of the tokens themselves, such as multi-line string
+/// literals.
+///
+/// [`newline`]: FaithfulOptions::newline
+/// [`indent`]: FaithfulOptions::indent
+/// [`max_blank_lines`]: FaithfulOptions::max_blank_lines
+/// [`trim_trailing_whitespace`]: FaithfulOptions::trim_trailing_whitespace
+///
+/// [`faithful_display`]: crate::faithful_display
+#[derive(Clone, Debug, Default)]
+pub struct FaithfulOptions {
+    pub(crate) fallback: SpanFallback,
+    pub(crate) comments: bool,
+    pub(crate) source_spelling: bool,
+    pub(crate) layout: Option<Arc<dyn LayoutStrategy + Send + Sync>>,
+    pub(crate) newline: Newline,
+    pub(crate) indent: Indent,
+    pub(crate) max_blank_lines: Option<usize>,
+    pub(crate) trim_trailing_whitespace: bool,
+    pub(crate) final_newline: bool,
+    pub(crate) first_line: FirstLine,
+    pub(crate) rebase: Option<usize>,
+    pub(crate) max_depth: Option<usize>,
+}
+
+impl FaithfulOptions {
+    /// Default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What to do when a token starts before the end of the previous one.
+    pub fn fallback(mut self, fallback: SpanFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Preserve comments by reading them back from the source.
+    ///
+    /// Comments are stripped by the lexer, so the text between two tokens is read from the source
+    /// file the tokens come from, and emitted verbatim if it only contains whitespaces and
+    /// comments. If it cannot be read – because the file is not known, for instance – whitespaces
+    /// are emitted instead.
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Spell identifiers and literals exactly as they are in the source.
+    ///
+    /// The textual form of identifiers and literals can differ from what was typed – unicode
+    /// identifiers are NFC-normalized, for instance. With this option, the source text of their
+    /// span is used instead, if available; their textual form is used otherwise.
+    pub fn source_spelling(mut self, source_spelling: bool) -> Self {
+        self.source_spelling = source_spelling;
+        self
+    }
+
+    /// Choose the whitespaces emitted between tokens with a [`LayoutStrategy`].
+    ///
+    /// By default, the layout of the input is respected, as with [`Faithful`].
+    ///
+    /// [`Faithful`]: crate::strategy::Faithful
+    pub fn layout<L>(mut self, layout: L) -> Self
+    where
+        L: LayoutStrategy + Send + Sync + 'static,
+    {
+        self.layout = Some(Arc::new(layout));
+        self
+    }
+
+    /// Style of the newlines.
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Characters the indentation of lines is made of.
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Collapse consecutive blank lines, keeping at most `max` of them.
+    pub fn max_blank_lines(mut self, max: usize) -> Self {
+        self.max_blank_lines = Some(max);
+        self
+    }
+
+    /// Remove the whitespaces at the end of lines.
+    ///
+    /// Whitespaces only end up at the end of lines when comments are preserved, or inside
+    /// multi-line tokens – which are never trimmed.
+    pub fn trim_trailing_whitespace(mut self, trim: bool) -> Self {
+        self.trim_trailing_whitespace = trim;
+        self
+    }
+
+    /// End the output with a newline, unless it’s empty or already ends with one.
+    pub fn final_newline(mut self, final_newline: bool) -> Self {
+        self.final_newline = final_newline;
+        self
+    }
+
+    /// Where the first line of the output starts.
+    pub fn first_line(mut self, first_line: FirstLine) -> Self {
+        self.first_line = first_line;
+        self
+    }
+
+    /// Strip the indentation common to all the lines, as `indoc!` does.
+    ///
+    /// The smallest column at which a line starts – the line of the first token included – is
+    /// removed from the indentation of every line, so that the output is flush-left wherever the
+    /// tokens are in the source. The content of multi-line tokens is left untouched.
+    ///
+    /// Only the indentation respecting the input is stripped: lines laid out by a
+    /// [`LayoutStrategy`] keep the indentation it chose.
+    pub fn dedent(mut self, dedent: bool) -> Self {
+        self.rebase = dedent.then_some(0);
+        self
+    }
+
+    /// Re-indent the lines onto `column`, preserving their relative indentation.
+    ///
+    /// The smallest column at which a line starts is moved to `column`, and every other line
+    /// moves along with it. This is [`dedent`](FaithfulOptions::dedent) followed by an indentation
+    /// of `column` columns; the first line is only indented if it’s [anchored]. Lines laid out by
+    /// a [`LayoutStrategy`] are moved by `column` columns, keeping the indentation it chose.
+    ///
+    /// [anchored]: FirstLine::Anchored
+    pub fn reindent(mut self, column: usize) -> Self {
+        self.rebase = Some(column);
+        self
+    }
+
+    /// Fail with [`FaithfulError::TooDeep`] on groups nested deeper than `max_depth`.
+    ///
+    /// Groups are displayed without recursion, so deeply nested groups cannot overflow the stack;
+    /// this bounds the memory and time spent on pathological inputs. Invisible groups count as
+    /// well. By default, the depth is not limited.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Create a [`Display`] object out of a token stream with these options.
+    ///
+    /// If the options make rendering fail – see [`SpanFallback::Strict`] –, formatting fails with
+    /// [`fmt::Error`]. Beware that [`ToString::to_string`] panics in that case; use
+    /// [`FaithfulOptions::try_string`] to learn why the tokens could not be displayed instead.
+    pub fn display<'a, T>(&self, stream: &'a T) -> Displayed<'a, T>
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        Displayed {
+            stream,
+            options: self.clone(),
+        }
+    }
+
+    /// Create a [`Display`] object out of an owned token stream with these options.
+    ///
+    /// The token stream is not cloned – only the stream of each group is, once every time it’s
+    /// displayed. Errors are reported as with [`FaithfulOptions::display`].
+    pub fn display_owned<T>(&self, stream: T) -> DisplayedOwned<T::Token>
+    where
+        T: FaithfulDisplay,
+    {
+        DisplayedOwned {
+            tokens: stream.into_faithful_tokens().collect(),
+            options: self.clone(),
+        }
+    }
+
+    /// Display a token stream into a [`fmt::Write`] with these options, reporting why it could
+    /// not be displayed faithfully, if so.
+    pub fn try_write<T, W>(
+        &self,
+        stream: &T,
+        out: &mut W,
+    ) -> Result<(), FaithfulError<<T::Token as Token>::Span>>
+    where
+        T: FaithfulDisplay + ?Sized,
+        W: fmt::Write,
+    {
+        let min_indent = || min_indent::<T::Token>(stream.faithful_tokens());
+
+        if let Some(mut renderer) = self.renderer(out, stream.faithful_start(), min_indent) {
+            renderer.stream(stream.faithful_tokens())?;
+            renderer.finish()?;
+        }
+
+        Ok(())
+    }
+
+    /// Display a token stream into an [`io::Write`] with these options, without buffering the
+    /// whole output.
+    ///
+    /// If the options make rendering fail – see [`SpanFallback::Strict`] –, an error of kind
+    /// [`io::ErrorKind::InvalidData`] is returned; use [`FaithfulOptions::try_write`] to get the
+    /// span of the offending token instead.
+    pub fn write_io<T, W>(&self, stream: &T, out: &mut W) -> io::Result<()>
+    where
+        T: FaithfulDisplay + ?Sized,
+        W: io::Write,
+    {
+        let mut writer = IoWriter::new(out);
+
+        self.try_write(stream, &mut writer).map_err(|err| {
+            writer
+                .error
+                .take()
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+        })
+    }
+
+    /// Display a token stream into a [`String`] with these options, reporting why it could not be
+    /// displayed faithfully, if so.
+    ///
+    /// The string is allocated up-front from the extent of the spans of the tokens.
+    pub fn try_string<T>(
+        &self,
+        stream: &T,
+    ) -> Result<String, FaithfulError<<T::Token as Token>::Span>>
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        let mut output = String::with_capacity(extent::<T::Token>(stream.faithful_tokens()));
+        self.try_write(stream, &mut output)?;
+        Ok(output)
+    }
+
+    /// Display a token stream into a [`String`] with these options.
+    ///
+    /// The string is allocated up-front from the extent of the spans of the tokens. Rendering
+    /// errors are ignored: the output is as complete as possible.
+    pub fn string<T>(&self, stream: &T) -> String
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        let mut output = String::with_capacity(extent::<T::Token>(stream.faithful_tokens()));
+        let _ = self.try_write(stream, &mut output);
+        output
+    }
+
+    /// Display an owned token stream into a [`String`] with these options, like
+    /// [`FaithfulOptions::string`], without cloning it.
+    pub fn into_string<T>(&self, stream: T) -> String
+    where
+        T: FaithfulDisplay,
+    {
+        let tokens: Vec<_> = stream.into_faithful_tokens().collect();
+        let mut output = String::with_capacity(extent::<T::Token>(&tokens));
+
+        let first = first_start::<T::Token>(&tokens);
+        let min_indent = || min_indent::<T::Token>(&tokens);
+
+        if let Some(mut renderer) = self.renderer::<_, T::Token>(&mut output, first, min_indent) {
+            let _ = renderer.stream(&tokens);
+            let _ = renderer.finish();
+        }
+
+        output
+    }
+
+    /// Display a token stream into a [`String`] with these options, along with a [`SourceMap`]
+    /// mapping every emitted token back to its span.
+    ///
+    /// Rendering errors are ignored: the output is as complete as possible.
+    pub fn string_with_map<T>(&self, stream: &T) -> (String, SourceMap<T::Token>)
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        let mut output = String::with_capacity(extent::<T::Token>(stream.faithful_tokens()));
+        let mut mappings = Vec::new();
+
+        let min_indent = || min_indent::<T::Token>(stream.faithful_tokens());
+
+        if let Some(renderer) = self.renderer(&mut output, stream.faithful_start(), min_indent) {
+            let mut renderer = renderer.record_mappings();
+            let _ = renderer.stream(stream.faithful_tokens());
+            let _ = renderer.finish();
+            mappings = renderer.into_mappings();
+        }
+
+        let map = SourceMap::new(mappings, &output);
+        (output, map)
+    }
+
+    /// Iterate over the characters of the rendering of a token stream with these options, each
+    /// with the span it comes from.
+    ///
+    /// Rendering errors are ignored: the output is as complete as possible.
+    pub fn chars<T>(&self, stream: &T) -> FaithfulChars<T::Token>
+    where
+        T: FaithfulDisplay + ?Sized,
+    {
+        let (output, map) = self.string_with_map(stream);
+        FaithfulChars::new(output, map)
+    }
+
+    /// Renderer of a token stream writing to `out`, if the stream has tokens.
+    ///
+    /// `first` is the position of the first token; the rendering starts there, so that no leading
+    /// whitespace is emitted – unless the first line is anchored, in which case it starts at the
+    /// beginning of its line. `min_indent` computes the smallest indentation of the lines, if a
+    /// first pass over the tokens is needed.
+    pub(crate) fn renderer<'a, W, K>(
+        &'a self,
+        out: &'a mut W,
+        first: Option<LineColumn>,
+        min_indent: impl FnOnce() -> Option<usize>,
+    ) -> Option<Renderer<'a, W, K>>
+    where
+        W: fmt::Write,
+        K: Token,
+    {
+        let first = first?;
+        let start = match self.first_line {
+            FirstLine::Unanchored => first,
+            FirstLine::Anchored => LineColumn { column: 0, ..first },
+        };
+
+        let mut renderer = Renderer::new(out, start, self);
+        if let Some(column) = self.rebase {
+            let min = min_indent().unwrap_or(0);
+            renderer = renderer.rebase(min, column);
+        }
+
+        Some(renderer)
+    }
+}
+
+impl From<SpanFallback> for FaithfulOptions {
+    fn from(fallback: SpanFallback) -> Self {
+        FaithfulOptions::new().fallback(fallback)
+    }
+}
+
+/// A token stream displayed with [`FaithfulOptions`].
+///
+/// This is the [`Display`] object returned by [`FaithfulOptions::display`] and
+/// [`faithful_display`]; it can be cloned and displayed as many times as needed.
+///
+/// [`faithful_display`]: crate::faithful_display
+pub struct Displayed<'a, T>
+where
+    T: ?Sized,
+{
+    stream: &'a T,
+    options: FaithfulOptions,
+}
+
+impl<T> Displayed<'_, T>
+where
+    T: ?Sized,
+{
+    /// Options the token stream is displayed with.
+    pub fn options(&self) -> &FaithfulOptions {
+        &self.options
+    }
+}
+
+impl<T> Clone for Displayed<'_, T>
+where
+    T: ?Sized,
+{
+    fn clone(&self) -> Self {
+        Displayed {
+            stream: self.stream,
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<T> Display for Displayed<'_, T>
+where
+    T: FaithfulDisplay + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let first = self.stream.faithful_start();
+        let min_indent = || min_indent::<T::Token>(self.stream.faithful_tokens());
+
+        if let Some(mut renderer) = self.options.renderer(f, first, min_indent) {
+            renderer
+                .stream(self.stream.faithful_tokens())
+                .and_then(|()| renderer.finish())
+                .map_err(|_| fmt::Error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An owned token stream displayed with [`FaithfulOptions`].
+///
+/// This is the [`Display`] object returned by [`FaithfulOptions::display_owned`] and
+/// [`faithful_display_owned`]; it can be cloned and displayed as many times as needed.
+///
+/// [`faithful_display_owned`]: crate::faithful_display_owned
+#[derive(Clone)]
+pub struct DisplayedOwned<T> {
+    tokens: Vec<T>,
+    options: FaithfulOptions,
+}
+
+impl<T> DisplayedOwned<T> {
+    /// Options the token stream is displayed with.
+    pub fn options(&self) -> &FaithfulOptions {
+        &self.options
+    }
+}
+
+impl<T> Display for DisplayedOwned<T>
+where
+    T: Token,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let first = first_start::<T>(&self.tokens);
+        let min_indent = || min_indent::<T>(&self.tokens);
+
+        if let Some(mut renderer) = self.options.renderer::<_, T>(f, first, min_indent) {
+            renderer
+                .stream(&self.tokens)
+                .and_then(|()| renderer.finish())
+                .map_err(|_| fmt::Error)?;
+        }
+
+        Ok(())
+    }
+}
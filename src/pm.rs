@@ -0,0 +1,109 @@
+//! [`Token`] implementation for the [proc_macro] types.
+
+use proc_macro::{Span, TokenStream, TokenTree};
+use std::path::PathBuf;
+
+use crate::token::{Delimiter, SourceSpan, Spacing, Token, TokenKind};
+use crate::LineColumn;
+
+impl_faithful_display!(proc_macro);
+
+impl SourceSpan for Span {
+    fn start(&self) -> LineColumn {
+        line_column(Span::start(self))
+    }
+
+    fn end(&self) -> LineColumn {
+        line_column(Span::end(self))
+    }
+
+    fn file(&self) -> Option<String> {
+        Some(Span::file(self))
+    }
+
+    fn local_file(&self) -> Option<PathBuf> {
+        Span::local_file(self)
+    }
+
+    fn source_text(&self) -> Option<String> {
+        Span::source_text(self)
+    }
+
+    #[cfg(feature = "nightly")]
+    fn join(&self, other: &Self) -> Option<Self> {
+        Span::join(self, *other)
+    }
+
+    fn is_call_site(&self) -> bool {
+        let call_site = Span::call_site();
+        SourceSpan::start(self) == SourceSpan::start(&call_site)
+            && SourceSpan::end(self) == SourceSpan::end(&call_site)
+    }
+}
+
+impl Token for TokenTree {
+    type Span = Span;
+    type Stream = TokenStream;
+
+    fn span(&self) -> Span {
+        TokenTree::span(self)
+    }
+
+    fn kind(&self) -> TokenKind<Span, TokenStream> {
+        match self {
+            TokenTree::Group(gr) => TokenKind::Group {
+                delimiter: gr.delimiter().into(),
+                open: gr.span_open(),
+                close: gr.span_close(),
+                stream: gr.stream(),
+            },
+            TokenTree::Ident(_) => TokenKind::Ident,
+            TokenTree::Punct(p) => TokenKind::Punct(p.as_char(), p.spacing().into()),
+            TokenTree::Literal(_) => TokenKind::Literal,
+        }
+    }
+
+    fn delimiter(&self) -> Option<Delimiter> {
+        match self {
+            TokenTree::Group(gr) => Some(gr.delimiter().into()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    fn subspan(&self, range: std::ops::Range<usize>) -> Option<Span> {
+        match self {
+            TokenTree::Literal(lit) => lit.subspan(range),
+            _ => None,
+        }
+    }
+}
+
+impl From<proc_macro::Spacing> for Spacing {
+    fn from(spacing: proc_macro::Spacing) -> Self {
+        match spacing {
+            proc_macro::Spacing::Alone => Spacing::Alone,
+            proc_macro::Spacing::Joint => Spacing::Joint,
+        }
+    }
+}
+
+impl From<proc_macro::Delimiter> for Delimiter {
+    fn from(delimiter: proc_macro::Delimiter) -> Self {
+        match delimiter {
+            proc_macro::Delimiter::Parenthesis => Delimiter::Parenthesis,
+            proc_macro::Delimiter::Brace => Delimiter::Brace,
+            proc_macro::Delimiter::Bracket => Delimiter::Bracket,
+            proc_macro::Delimiter::None => Delimiter::None,
+        }
+    }
+}
+
+/// Position of a (collapsed) span.
+fn line_column(span: Span) -> LineColumn {
+    // proc_macro columns are 1-indexed
+    LineColumn {
+        line: span.line(),
+        column: span.column().saturating_sub(1),
+    }
+}
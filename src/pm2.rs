@@ -0,0 +1,111 @@
+//! [`Token`] implementation for the [proc-macro2] types.
+//!
+//! Line and column information is taken from the `span-locations` feature of [proc-macro2].
+//!
+//! [proc-macro2]: https://crates.io/crates/proc-macro2
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::token::{Delimiter, SourceSpan, Spacing, Token, TokenKind};
+use crate::LineColumn;
+
+impl_faithful_display!(proc_macro2);
+
+impl From<proc_macro2::LineColumn> for LineColumn {
+    fn from(lc: proc_macro2::LineColumn) -> Self {
+        LineColumn {
+            line: lc.line,
+            column: lc.column,
+        }
+    }
+}
+
+impl SourceSpan for Span {
+    fn start(&self) -> LineColumn {
+        Span::start(self).into()
+    }
+
+    fn end(&self) -> LineColumn {
+        Span::end(self).into()
+    }
+
+    fn file(&self) -> Option<String> {
+        Some(Span::file(self))
+    }
+
+    fn local_file(&self) -> Option<PathBuf> {
+        Span::local_file(self)
+    }
+
+    fn source_text(&self) -> Option<String> {
+        Span::source_text(self)
+    }
+
+    fn join(&self, other: &Self) -> Option<Self> {
+        Span::join(self, *other)
+    }
+
+    fn is_call_site(&self) -> bool {
+        let call_site = Span::call_site();
+        Span::start(self) == call_site.start() && Span::end(self) == call_site.end()
+    }
+}
+
+impl Token for TokenTree {
+    type Span = Span;
+    type Stream = TokenStream;
+
+    fn span(&self) -> Span {
+        TokenTree::span(self)
+    }
+
+    fn kind(&self) -> TokenKind<Span, TokenStream> {
+        match self {
+            TokenTree::Group(gr) => TokenKind::Group {
+                delimiter: gr.delimiter().into(),
+                open: gr.span_open(),
+                close: gr.span_close(),
+                stream: gr.stream(),
+            },
+            TokenTree::Ident(_) => TokenKind::Ident,
+            TokenTree::Punct(p) => TokenKind::Punct(p.as_char(), p.spacing().into()),
+            TokenTree::Literal(_) => TokenKind::Literal,
+        }
+    }
+
+    fn delimiter(&self) -> Option<Delimiter> {
+        match self {
+            TokenTree::Group(gr) => Some(gr.delimiter().into()),
+            _ => None,
+        }
+    }
+
+    fn subspan(&self, range: Range<usize>) -> Option<Span> {
+        match self {
+            TokenTree::Literal(lit) => lit.subspan(range),
+            _ => None,
+        }
+    }
+}
+
+impl From<proc_macro2::Spacing> for Spacing {
+    fn from(spacing: proc_macro2::Spacing) -> Self {
+        match spacing {
+            proc_macro2::Spacing::Alone => Spacing::Alone,
+            proc_macro2::Spacing::Joint => Spacing::Joint,
+        }
+    }
+}
+
+impl From<proc_macro2::Delimiter> for Delimiter {
+    fn from(delimiter: proc_macro2::Delimiter) -> Self {
+        match delimiter {
+            proc_macro2::Delimiter::Parenthesis => Delimiter::Parenthesis,
+            proc_macro2::Delimiter::Brace => Delimiter::Brace,
+            proc_macro2::Delimiter::Bracket => Delimiter::Bracket,
+            proc_macro2::Delimiter::None => Delimiter::None,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Positions and regions of source files, owned by the crate rather than by a backend.
+
+use crate::token::SourceSpan;
+
+/// A line and column in a source file.
+///
+/// Lines are 1-indexed and columns are 0-indexed, whatever the backend the position comes from.
+/// Positions are ordered by line first, then by column.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineColumn {
+    /// 1-indexed line.
+    pub line: usize,
+    /// 0-indexed column, in characters.
+    pub column: usize,
+}
+
+/// A position in a source file; another name for [`LineColumn`].
+pub type Position = LineColumn;
+
+impl LineColumn {
+    /// Position at `line` – 1-indexed – and `column` – 0-indexed.
+    pub const fn new(line: usize, column: usize) -> Self {
+        LineColumn { line, column }
+    }
+
+    /// Whitespaces laying out a token starting at `to` after a token ending at this position, if
+    /// `to` is not before it.
+    pub fn distance_to(self, to: LineColumn) -> Option<Distance> {
+        if to < self {
+            return None;
+        }
+
+        let distance = if to.line == self.line {
+            Distance {
+                lines: 0,
+                columns: to.column - self.column,
+            }
+        } else {
+            Distance {
+                lines: to.line - self.line,
+                columns: to.column,
+            }
+        };
+
+        Some(distance)
+    }
+}
+
+/// Whitespaces between two positions: newlines, then spaces.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Distance {
+    /// Number of newlines.
+    pub lines: usize,
+    /// Number of columns, from the first position if on the same line, from the start of the line
+    /// otherwise.
+    pub columns: usize,
+}
+
+/// A region of a source file, from a start position – included – to an end one – excluded.
+///
+/// Regions are ordered by start first, then by end. They implement [`SourceSpan`], so that they
+/// can stand for the spans of tokens once the tokens are gone.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    /// Position at which the region starts.
+    pub start: LineColumn,
+    /// Position at which the region ends.
+    pub end: LineColumn,
+}
+
+impl Region {
+    /// Region from `start` to `end`.
+    pub const fn new(start: LineColumn, end: LineColumn) -> Self {
+        Region { start, end }
+    }
+
+    /// Region of a span.
+    pub fn of(span: &impl SourceSpan) -> Self {
+        Region {
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+
+    /// Whether the region is empty.
+    pub fn is_empty(self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Whether `pos` is in the region.
+    pub fn contains(self, pos: LineColumn) -> bool {
+        self.start <= pos && pos < self.end
+    }
+
+    /// Whether `other` is entirely in the region.
+    pub fn contains_region(self, other: Region) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Whether the region and `other` have positions in common.
+    pub fn overlaps(self, other: Region) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// Smallest region covering both the region and `other`.
+    pub fn cover(self, other: Region) -> Region {
+        Region {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Whitespaces laying out `next` after the region, if `next` doesn’t start before its end.
+    pub fn distance_to(self, next: Region) -> Option<Distance> {
+        self.end.distance_to(next.start)
+    }
+}
+
+impl SourceSpan for Region {
+    fn start(&self) -> LineColumn {
+        self.start
+    }
+
+    fn end(&self) -> LineColumn {
+        self.end
+    }
+}
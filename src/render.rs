@@ -0,0 +1,1194 @@
+//! Faithful rendering of [`Token`]s, whatever the backend they come from.
+
+use std::borrow::Borrow;
+use std::fmt::{self, Write};
+use std::iter::{Chain, Peekable};
+use std::marker::PhantomData;
+use std::vec;
+
+use crate::doc;
+use crate::error::FaithfulError;
+use crate::options::{FaithfulOptions, FirstLine, Indent, Newline, SpanFallback};
+use crate::source::{self, Sources};
+use crate::source_map::Mapping;
+use crate::strategy::{self, Gap, Lexeme, Whitespace};
+use crate::token::{Delimiter, SourceSpan, Spacing, Token, TokenKind};
+use crate::{Distance, LineColumn, Region};
+
+/// Result of rendering tokens of type `T`.
+pub(crate) type RenderResult<T, R = ()> = Result<R, FaithfulError<<T as Token>::Span>>;
+
+/// Faithful renderer.
+///
+/// The renderer accumulates the position at which the last token ended, so that whitespaces can
+/// be inserted to respect the initial layout of the next token.
+pub(crate) struct Renderer<'a, W, T>
+where
+    T: Token,
+{
+    out: Output<'a, W>,
+    options: &'a FaithfulOptions,
+    prev: LineColumn,
+    /// Span of the last emitted token.
+    prev_span: Option<T::Span>,
+    /// Last emitted token, if any.
+    last: Option<Lexeme>,
+    /// Whether the last emitted token left the position untouched, its layout being unknown.
+    detached: bool,
+    /// Delimiters of the groups enclosing the current token, the innermost last.
+    enclosing: Vec<Delimiter>,
+    /// Source file of the first token; only tracked with [`SpanFallback::Strict`].
+    file: Option<String>,
+    /// Mappings of the emitted tokens, if recorded.
+    mappings: Option<Vec<Mapping<T>>>,
+    /// Source files, read to preserve comments.
+    sources: Sources,
+    _token: PhantomData<T>,
+}
+
+impl<'a, W, T> Renderer<'a, W, T>
+where
+    W: Write,
+    T: Token,
+{
+    /// Create a renderer writing to `out`, starting at the `prev` position.
+    pub(crate) fn new(out: &'a mut W, prev: LineColumn, options: &'a FaithfulOptions) -> Self {
+        Renderer {
+            out: Output::new(out, options),
+            options,
+            prev,
+            prev_span: None,
+            last: None,
+            detached: false,
+            enclosing: Vec::new(),
+            file: None,
+            mappings: None,
+            sources: Sources::default(),
+            _token: PhantomData,
+        }
+    }
+
+    /// Record a [`Mapping`] for every emitted token.
+    pub(crate) fn record_mappings(mut self) -> Self {
+        self.mappings = Some(Vec::new());
+        self
+    }
+
+    /// Move the indentation of every line from the `from` column to the `to` one.
+    pub(crate) fn rebase(mut self, from: usize, to: usize) -> Self {
+        self.out.dedent = from;
+        self.out.reindent = to;
+        self
+    }
+
+    /// Mappings recorded so far.
+    pub(crate) fn into_mappings(self) -> Vec<Mapping<T>> {
+        self.mappings.unwrap_or_default()
+    }
+
+    /// Position at which the last token ended.
+    pub(crate) fn position(&self) -> LineColumn {
+        self.prev
+    }
+
+    /// Finish the output, once all the tokens were displayed.
+    pub(crate) fn finish(&mut self) -> RenderResult<T> {
+        Ok(self.out.finish()?)
+    }
+
+    /// Display a stream of tokens.
+    ///
+    /// Tokens are only borrowed; the stream of each group is cloned once, when displaying it.
+    /// Doc comments are displayed as comments rather than as the `#[doc = "…"]` attributes they
+    /// reach procedural macros as.
+    ///
+    /// Groups are traversed with an explicit stack of the groups being displayed rather than
+    /// recursively, so that deeply nested groups cannot overflow the stack.
+    pub(crate) fn stream<I>(&mut self, stream: I) -> RenderResult<T>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        let mut stream = stream.into_iter().peekable();
+        let mut groups: Vec<OpenGroup<T>> = Vec::new();
+
+        loop {
+            let depth = groups.len();
+            let opened = match groups.last_mut() {
+                Some(group) => match next_tree(&mut group.stream) {
+                    Some(tree) => self.tree(tree, next_start(&mut group.stream), depth)?,
+                    None => {
+                        let group = groups.pop().expect("open group");
+                        self.close(group)?;
+                        continue;
+                    }
+                },
+                None => match next_tree(&mut stream) {
+                    Some(tree) => self.tree(tree, next_start(&mut stream), depth)?,
+                    None => return Ok(()),
+                },
+            };
+
+            groups.extend(opened);
+        }
+    }
+
+    /// Display tokens flattened beforehand, given their text, span and lexeme, in order.
+    ///
+    /// Delimiters are displayed as tokens; like groups, a delimited sequence starting before the
+    /// end of the previous token doesn’t move the position.
+    pub(crate) fn flat<'t>(
+        &mut self,
+        tokens: impl IntoIterator<Item = (&'t str, T::Span, Lexeme)>,
+    ) -> RenderResult<T> {
+        // positions to restore when closing the delimited sequences being displayed
+        let mut restore = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some((text, span, lexeme)) = tokens.next() {
+            let prev = self.prev;
+            let (synthetic, in_order) = match lexeme {
+                Lexeme::Open(_) | Lexeme::Close(_) => (false, span.start() >= prev),
+                _ => {
+                    let next = tokens.peek().map(|(_, next, _)| next.start());
+                    let synthetic = synthetic(&span, next);
+                    (synthetic, !synthetic && self.advances(&span))
+                }
+            };
+
+            if let Lexeme::Close(_) = lexeme {
+                self.enclosing.pop();
+            }
+
+            self.check(&span)?;
+            self.whitespace_adjust_span(&span, lexeme, synthetic)?;
+            self.emit(&span, lexeme, None, |out| out.write_str(text))?;
+            self.prev = span.end();
+            self.detached = false;
+
+            match lexeme {
+                Lexeme::Open(delimiter) => {
+                    self.enclosing.push(delimiter);
+                    restore.push((!in_order).then_some(prev));
+                }
+
+                Lexeme::Close(_) => {
+                    if let Some(Some(prev)) = restore.pop() {
+                        self.prev = prev;
+                        self.detached = true;
+                    }
+                }
+
+                _ if !in_order => {
+                    self.prev = prev;
+                    self.detached = true;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Display a token tree of a stream, followed by a token starting at `next`, with `depth`
+    /// groups open; the group it opens, if any, is returned so that its tokens are displayed next.
+    fn tree<B>(
+        &mut self,
+        tree: Tree<B, T>,
+        next: Option<LineColumn>,
+        depth: usize,
+    ) -> RenderResult<T, Option<OpenGroup<T>>>
+    where
+        B: Borrow<T>,
+    {
+        match tree {
+            Tree::Token(token, kind) => self.render(token.borrow(), kind, next, depth),
+            Tree::Attribute { hash, bang, group } => self.attribute(
+                hash.borrow(),
+                bang.as_ref().map(Borrow::borrow),
+                group.as_ref().map(Borrow::borrow),
+                depth,
+            ),
+        }
+    }
+
+    /// Display an attribute – `#`, an optional `!` and the group following them if it has the
+    /// span of a doc comment –, as a doc comment if it’s one.
+    fn attribute(
+        &mut self,
+        hash: &T,
+        bang: Option<&T>,
+        group: Option<&T>,
+        depth: usize,
+    ) -> RenderResult<T, Option<OpenGroup<T>>> {
+        let Some(group) = group else {
+            self.token(hash)?;
+            return bang.map_or(Ok(None), |bang| self.token(bang));
+        };
+
+        let TokenKind::Group {
+            delimiter,
+            open,
+            close,
+            stream,
+        } = group.kind()
+        else {
+            self.token(hash)?;
+            if let Some(bang) = bang {
+                self.token(bang)?;
+            }
+            return self.token(group);
+        };
+
+        // only the first tokens are needed to recognize a doc comment
+        let mut stream = stream.into_iter();
+        let head: Vec<T> = stream.by_ref().take(4).collect();
+
+        if let Some(text) = doc::doc_comment(bang.is_some(), delimiter, &head) {
+            self.doc_comment(&hash.span(), &text)?;
+            return Ok(None);
+        }
+
+        self.token(hash)?;
+        if let Some(bang) = bang {
+            self.token(bang)?;
+        }
+
+        let stream = head.into_iter().chain(stream).peekable();
+        self.open(group, delimiter, (open, close), stream, depth)
+            .map(Some)
+    }
+
+    /// Display a single token outside of any group.
+    ///
+    /// The tokens of a group are not displayed: the group is returned instead.
+    fn token(&mut self, token: &T) -> RenderResult<T, Option<OpenGroup<T>>> {
+        self.render(token, token.kind(), None, 0)
+    }
+
+    /// Display a single token, which kind is already known, followed by a token starting at
+    /// `next`, with `depth` groups open.
+    ///
+    /// A group is only opened: its opening delimiter is displayed, and the group is returned so
+    /// that its tokens – and its closing delimiter – are displayed next.
+    ///
+    /// A token which layout cannot be respected – starting before the end of the previous one,
+    /// or [synthetic](synthetic) – doesn’t move the position, so that the tokens following it
+    /// are laid out relatively to the last token in order.
+    fn render(
+        &mut self,
+        token: &T,
+        kind: TokenKind<T::Span, T::Stream>,
+        next: Option<LineColumn>,
+        depth: usize,
+    ) -> RenderResult<T, Option<OpenGroup<T>>> {
+        let prev = self.prev;
+        let span = token.span();
+        let synthetic = synthetic(&span, next);
+        let in_order = !synthetic && self.advances(&span);
+
+        match kind {
+            TokenKind::Group {
+                delimiter,
+                open,
+                close,
+                stream,
+            } => {
+                let stream = Vec::new().into_iter().chain(stream).peekable();
+                return self
+                    .open(token, delimiter, (open, close), stream, depth)
+                    .map(Some);
+            }
+
+            TokenKind::Punct(c, spacing) => {
+                let lexeme = Lexeme::Punct {
+                    ch: c,
+                    spacing,
+                    joined: matches!(
+                        self.last,
+                        Some(Lexeme::Punct {
+                            spacing: Spacing::Joint,
+                            ..
+                        })
+                    ),
+                };
+
+                self.check(&span)?;
+                self.whitespace_adjust_span(&span, lexeme, synthetic)?;
+
+                self.emit(&span, lexeme, None, |out| out.write_char(c))?;
+                self.prev = span.end();
+            }
+
+            kind @ (TokenKind::Ident | TokenKind::Literal) => {
+                self.check(&span)?;
+                let (lexeme, literal) = match kind {
+                    TokenKind::Literal => (Lexeme::Literal, Some(token)),
+                    _ => (Lexeme::Ident, None),
+                };
+                self.whitespace_adjust_span(&span, lexeme, synthetic)?;
+
+                let spelling = self
+                    .options
+                    .source_spelling
+                    .then(|| source::spelling(token))
+                    .flatten();
+
+                self.emit(&span, lexeme, literal, |out| match spelling {
+                    Some(spelling) => out.write_str(&spelling),
+                    None => write!(out, "{}", token),
+                })?;
+                self.prev = span.end();
+            }
+        }
+
+        if !in_order {
+            self.prev = prev;
+        }
+        self.detached = !in_order;
+
+        Ok(None)
+    }
+
+    /// Open a group, with `depth` groups already open, displaying its opening delimiter.
+    ///
+    /// Invisible groups – such as `$x:expr` captures forwarded by `macro_rules!` – are
+    /// transparent: their tokens are laid out as if they were not grouped.
+    fn open(
+        &mut self,
+        group: &T,
+        delimiter: Delimiter,
+        (open, close): (T::Span, T::Span),
+        stream: Peekable<GroupStream<T>>,
+        depth: usize,
+    ) -> RenderResult<T, OpenGroup<T>> {
+        if self
+            .options
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            return Err(FaithfulError::TooDeep(group.span()));
+        }
+
+        let Some((del_first, del_end)) = delimiter.chars() else {
+            return Ok(OpenGroup {
+                stream,
+                close: None,
+                restore: None,
+            });
+        };
+
+        let prev = self.prev;
+        let in_order = group.span().start() >= prev;
+
+        self.check(&open)?;
+
+        // checked before the position, which delimiters without a span cannot respect
+        if self.options.fallback == SpanFallback::Strict
+            && !(is_delimiter(&open) && is_delimiter(&close))
+        {
+            return Err(FaithfulError::MissingDelimiterSpan(group.span()));
+        }
+
+        self.whitespace_adjust_span(&open, Lexeme::Open(delimiter), false)?;
+
+        self.emit(&open, Lexeme::Open(delimiter), None, |out| {
+            out.write_char(del_first)
+        })?;
+        self.prev = open.end();
+        self.detached = false;
+        self.enclosing.push(delimiter);
+
+        Ok(OpenGroup {
+            stream,
+            close: Some((delimiter, del_end, close)),
+            restore: (!in_order).then_some(prev),
+        })
+    }
+
+    /// Close a group once all its tokens were displayed, displaying its closing delimiter.
+    fn close(&mut self, group: OpenGroup<T>) -> RenderResult<T> {
+        if let Some((delimiter, del_end, close)) = group.close {
+            self.enclosing.pop();
+
+            let lexeme = Lexeme::Close(delimiter);
+            self.check(&close)?;
+            self.whitespace_adjust_span(&close, lexeme, false)?;
+            self.emit(&close, lexeme, None, |out| out.write_char(del_end))?;
+            self.prev = close.end();
+            self.detached = false;
+        }
+
+        if let Some(prev) = group.restore {
+            self.prev = prev;
+            self.detached = true;
+        }
+
+        Ok(())
+    }
+
+    /// Display a doc comment, which span is `span`.
+    fn doc_comment(&mut self, span: &T::Span, text: &str) -> RenderResult<T> {
+        let prev = self.prev;
+        let in_order = span.start() >= prev;
+
+        let lexeme = Lexeme::Comment {
+            line: text.starts_with("//"),
+        };
+
+        self.check(span)?;
+        self.whitespace_adjust_span(span, lexeme, false)?;
+        self.emit(span, lexeme, None, |out| out.write_str(text))?;
+
+        self.prev = if in_order { span.end() } else { prev };
+        self.detached = !in_order;
+        Ok(())
+    }
+
+    /// Write a token to the output, recording its mapping if needed.
+    fn emit(
+        &mut self,
+        span: &T::Span,
+        lexeme: Lexeme,
+        literal: Option<&T>,
+        write: impl FnOnce(&mut Output<'a, W>) -> fmt::Result,
+    ) -> RenderResult<T> {
+        self.out.flush()?;
+        let start = self.out.len;
+        write(&mut self.out)?;
+        self.last = Some(lexeme);
+
+        if self.options.comments {
+            self.prev_span = Some(span.clone());
+        }
+
+        if let Some(mappings) = &mut self.mappings {
+            mappings.push(Mapping {
+                range: start..self.out.len,
+                span: span.clone(),
+                literal: literal.cloned(),
+                lexeme,
+                depth: self.enclosing.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// In strict mode, check that a span comes from the input and from the same file as the first
+    /// token.
+    fn check(&mut self, span: &T::Span) -> RenderResult<T> {
+        if self.options.fallback != SpanFallback::Strict {
+            return Ok(());
+        }
+
+        if span.is_call_site() {
+            return Err(FaithfulError::CallSite(span.clone()));
+        }
+
+        match (&self.file, span.file()) {
+            (Some(first), Some(file)) if *first != file => {
+                Err(FaithfulError::DifferentFile(span.clone()))
+            }
+
+            (None, file) => {
+                self.file = file;
+                Ok(())
+            }
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the layout of `span` can be respected: it must start after the previous token
+    /// and not be empty – unless nothing was emitted yet.
+    fn advances(&self, span: &T::Span) -> bool {
+        advances(self.prev, span.start(), span.end(), self.last.is_none())
+    }
+
+    /// Adjust the output with whitespaces up to the start of `span`, which is the span of `next`
+    /// – a [synthetic](synthetic) token if `synthetic` is set.
+    ///
+    /// If comments are preserved, the source text between the previous token and `span` is
+    /// emitted instead, if it can be found.
+    ///
+    /// The layout strategy is consulted first: the layout of the input is only respected if it
+    /// asks to.
+    fn whitespace_adjust_span(
+        &mut self,
+        span: &T::Span,
+        next: Lexeme,
+        synthetic: bool,
+    ) -> RenderResult<T> {
+        let in_order = !synthetic && self.advances(span);
+
+        if let Some(layout) = &self.options.layout {
+            let gap = Gap {
+                last: self.last,
+                next,
+                depth: self.enclosing.len(),
+                delimiter: self.enclosing.last().copied(),
+                from: self.prev,
+                to: span.start(),
+                in_order,
+            };
+
+            if let Whitespace::Exactly { newlines, spaces } = layout.whitespace(&gap) {
+                // whatever follows a line comment must be on the next line
+                let newlines = match self.last {
+                    Some(Lexeme::Comment { line: true }) => newlines.max(1),
+                    _ => newlines,
+                };
+
+                self.out.laid_out(newlines, spaces);
+                return Ok(());
+            }
+        }
+
+        if self.options.comments && in_order {
+            let gap = self
+                .prev_span
+                .as_ref()
+                .filter(|prev_span| prev_span.end() == self.prev)
+                .and_then(|prev_span| self.sources.between(prev_span, span));
+
+            if let Some(gap) = gap {
+                return Ok(self.out.gap(&gap)?);
+            }
+        }
+
+        self.whitespace_adjust(span, next, in_order)
+    }
+
+    /// Automatically adjust with whitespaces the output based on the current position and the
+    /// previous one.
+    ///
+    /// This function is key to the overall implementation, has it enables to respect the input
+    /// indentation and general formatting.
+    ///
+    /// If the layout of `span` cannot be respected — which happens with spans that were not
+    /// written by the user, for instance —, as signaled by `in_order`, the [`SpanFallback`] is
+    /// used instead, knowing that the next token is `next`. It is also used after such a token,
+    /// if the layout of the input doesn’t separate the next token from the last one in order.
+    fn whitespace_adjust(
+        &mut self,
+        span: &T::Span,
+        next: Lexeme,
+        in_order: bool,
+    ) -> RenderResult<T> {
+        if !in_order {
+            if self.options.fallback == SpanFallback::Strict {
+                return Err(FaithfulError::BackwardsSpan(span.clone()));
+            }
+
+            self.separate(next);
+            return Ok(());
+        }
+
+        // on the same line, only spaces are needed; on different lines, the newlines difference
+        // first, then the column of the token
+        match self.prev.distance_to(span.start()) {
+            Some(Distance {
+                lines: 0,
+                columns: 0,
+            }) if self.detached => self.separate(next),
+
+            Some(distance) => {
+                self.out.newlines(distance.lines);
+                self.out.spaces(distance.columns);
+            }
+
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Separate the last token, if any, from `next` with the [`SpanFallback`], their layout being
+    /// unknown.
+    fn separate(&mut self, next: Lexeme) {
+        let Some(last) = self.last else {
+            return;
+        };
+
+        if let Lexeme::Comment { line: true } = last {
+            // whatever follows a line comment must be on the next line
+            self.out.newlines(1);
+            return;
+        }
+
+        let space = match self.options.fallback {
+            SpanFallback::Space => true,
+            SpanFallback::Nothing | SpanFallback::Strict => false,
+            SpanFallback::Heuristic => strategy::separated(last, next),
+        };
+
+        if space {
+            self.out.spaces(1);
+        }
+    }
+}
+
+/// Iterator over the tokens of a group.
+type StreamIter<T> = <<T as Token>::Stream as IntoIterator>::IntoIter;
+
+/// Tokens of a group being displayed: the tokens already read from its stream, then the rest of
+/// its stream.
+type GroupStream<T> = Chain<vec::IntoIter<T>, StreamIter<T>>;
+
+/// A group being displayed, which tokens are displayed before its closing delimiter.
+struct OpenGroup<T>
+where
+    T: Token,
+{
+    stream: Peekable<GroupStream<T>>,
+    /// Delimiter, closing character and span of the closing delimiter, unless the group is
+    /// invisible.
+    close: Option<(Delimiter, char, T::Span)>,
+    /// Position to restore once the group is closed, if the group is out of order.
+    restore: Option<LineColumn>,
+}
+
+/// A token tree read from a stream.
+enum Tree<B, T>
+where
+    T: Token,
+{
+    /// A single token, and its kind.
+    Token(B, TokenKind<T::Span, T::Stream>),
+    /// A `#`, and the `!` and the group following it if they may make a doc comment.
+    Attribute {
+        hash: B,
+        bang: Option<B>,
+        group: Option<B>,
+    },
+}
+
+/// Read the next token tree of a stream.
+fn next_tree<T, I>(stream: &mut Peekable<I>) -> Option<Tree<I::Item, T>>
+where
+    T: Token,
+    I: Iterator,
+    I::Item: Borrow<T>,
+{
+    let tree = stream.next()?;
+    let kind = tree.borrow().kind();
+
+    let TokenKind::Punct('#', _) = kind else {
+        return Some(Tree::Token(tree, kind));
+    };
+
+    let hash = tree.borrow().span();
+    let bang = stream.next_if(|bang| {
+        let bang = bang.borrow();
+        bang.delimiter().is_none()
+            && matches!(bang.kind(), TokenKind::Punct('!', _))
+            && bang.span().start() == hash.start()
+    });
+    let group = stream.next_if(|group| doc::has_comment_span(&hash, group.borrow()));
+
+    Some(Tree::Attribute {
+        hash: tree,
+        bang,
+        group,
+    })
+}
+
+/// Start of the next token of a stream, if any and if its span is not the call-site one.
+fn next_start<T, I>(stream: &mut Peekable<I>) -> Option<LineColumn>
+where
+    T: Token,
+    I: Iterator,
+    I::Item: Borrow<T>,
+{
+    let span = stream.peek()?.borrow().span();
+    (!span.is_call_site()).then(|| span.start())
+}
+
+/// Whether the layout of a token from `start` to `end` can be respected after a token ending at
+/// `prev`: it must start after it and not be empty – unless it’s the `first` token.
+pub(crate) fn advances(prev: LineColumn, start: LineColumn, end: LineColumn, first: bool) -> bool {
+    start >= prev && (start < end || first)
+}
+
+/// Whether a token is synthetic – generated rather than written by the user –, given its span and
+/// the start of the token following it, if any.
+///
+/// A synthetic token has the call-site span, an empty span, or a span covering the start of the
+/// next token, as a span borrowed from an enclosing group – `quote! { #generated #input }` –
+/// does. The layout of such a token cannot be respected.
+fn synthetic(span: &impl SourceSpan, next: Option<LineColumn>) -> bool {
+    let (start, end) = (span.start(), span.end());
+    span.is_call_site() || start >= end || next.is_some_and(|next| covers(start, end, next))
+}
+
+/// Whether a token from `start` to `end` covers `pos`.
+fn covers(start: LineColumn, end: LineColumn, pos: LineColumn) -> bool {
+    start <= pos && pos < end
+}
+
+/// Position at which the first token of a stream starts, looking inside invisible groups.
+///
+/// Synthetic tokens are skipped, unless the stream is made of them only.
+pub(crate) fn first_start<T>(stream: impl IntoIterator<Item = impl Borrow<T>>) -> Option<LineColumn>
+where
+    T: Token,
+{
+    first_known_start(Spans::<_, T>::new(stream, false))
+}
+
+/// Position at which the first token which layout is known starts, given the starts and ends of
+/// the tokens, in order, and whether their span is the call-site one.
+///
+/// See [`first_start`].
+pub(crate) fn first_known_start(
+    spans: impl IntoIterator<Item = (LineColumn, LineColumn, bool)>,
+) -> Option<LineColumn> {
+    let mut spans = spans.into_iter().peekable();
+    let first = spans.peek().map(|&(start, _, _)| start);
+
+    known(spans).next().map(|(start, _)| start).or(first)
+}
+
+/// Starts and ends of the tokens which layout is known, given the starts and ends of the tokens,
+/// in order, and whether their span is the call-site one.
+///
+/// Synthetic tokens are skipped.
+fn known(
+    spans: impl IntoIterator<Item = (LineColumn, LineColumn, bool)>,
+) -> impl Iterator<Item = (LineColumn, LineColumn)> {
+    let mut spans = spans.into_iter().peekable();
+
+    std::iter::from_fn(move || loop {
+        let (start, end, call_site) = spans.next()?;
+        let next = spans
+            .peek()
+            .filter(|&&(_, _, call_site)| !call_site)
+            .map(|&(start, _, _)| start);
+
+        if start < end && !call_site && !next.is_some_and(|next| covers(start, end, next)) {
+            return Some((start, end));
+        }
+    })
+}
+
+/// Region covered by the tokens of a stream, looking inside invisible groups.
+pub(crate) fn region<T>(stream: impl IntoIterator<Item = impl Borrow<T>>) -> Option<Region>
+where
+    T: Token,
+{
+    Spans::<_, T>::new(stream, false)
+        .map(|(start, end, _)| Region::new(start, end))
+        .reduce(Region::cover)
+}
+
+/// Smallest column at which a line of a stream starts, looking inside groups.
+///
+/// Only the tokens starting a line are considered – the first token included; tokens which layout
+/// cannot be respected, synthetic ones included, are ignored.
+pub(crate) fn min_indent<T>(stream: impl IntoIterator<Item = impl Borrow<T>>) -> Option<usize>
+where
+    T: Token,
+{
+    min_start_column(Spans::<_, T>::new(stream, true))
+}
+
+/// Smallest column at which a line starts, given the starts and ends of the tokens, in order, and
+/// whether their span is the call-site one.
+///
+/// See [`min_indent`].
+pub(crate) fn min_start_column(
+    spans: impl IntoIterator<Item = (LineColumn, LineColumn, bool)>,
+) -> Option<usize> {
+    let mut prev: Option<LineColumn> = None;
+    let mut min: Option<usize> = None;
+
+    for (start, end) in known(spans) {
+        if prev.is_some_and(|prev| start < prev) {
+            continue;
+        }
+
+        if prev.is_none_or(|prev| start.line != prev.line) {
+            min = Some(min.map_or(start.column, |min| min.min(start.column)));
+        }
+        prev = Some(end);
+    }
+
+    min
+}
+
+/// Estimated length of the rendering of a stream, computed from the extent of its spans.
+///
+/// The estimate is exact for tokens laid out faithfully and made of ASCII characters, as long as
+/// the lines are neither dedented nor re-indented. Groups are looked inside, so that the text of
+/// their inner lines is measured.
+pub(crate) fn extent<T>(stream: impl IntoIterator<Item = impl Borrow<T>>) -> usize
+where
+    T: Token,
+{
+    let mut prev: Option<LineColumn> = None;
+    let mut len = 0;
+
+    for (start, end, _) in Spans::<_, T>::new(stream, true) {
+        let from = match prev {
+            // a separator, and a token which length is unknown
+            Some(prev) if start >= end || start < prev => {
+                len += 2;
+                continue;
+            }
+            Some(prev) => prev,
+            None => start,
+        };
+
+        if let Some(distance) = from.distance_to(end) {
+            len += distance.lines + distance.columns;
+        }
+        prev = Some(end);
+    }
+
+    len
+}
+
+/// Starts and ends of the tokens of a stream, in order, and whether their span is the call-site
+/// one, looking inside invisible groups.
+///
+/// Visible groups are either yielded whole, or looked inside as well – their delimiters being
+/// yielded as tokens; groups with the call-site span are always looked inside. Groups are
+/// traversed with an explicit stack, as the renderer does.
+struct Spans<I, T>
+where
+    T: Token,
+{
+    stream: I,
+    /// Streams of the groups being looked inside, and their closing delimiter if visible.
+    groups: Vec<(StreamIter<T>, Option<T::Span>)>,
+    /// Whether to look inside visible groups.
+    visible: bool,
+}
+
+impl<I, T> Spans<I, T>
+where
+    I: Iterator,
+    I::Item: Borrow<T>,
+    T: Token,
+{
+    fn new(stream: impl IntoIterator<IntoIter = I>, visible: bool) -> Self {
+        Spans {
+            stream: stream.into_iter(),
+            groups: Vec::new(),
+            visible,
+        }
+    }
+
+    /// Start and end of a token, unless it’s a group to look inside – which is entered instead.
+    fn enter(&mut self, tree: &T) -> Option<(LineColumn, LineColumn, bool)> {
+        match tree.delimiter() {
+            Some(Delimiter::None) => {
+                if let TokenKind::Group { stream, .. } = tree.kind() {
+                    self.groups.push((stream.into_iter(), None));
+                }
+                None
+            }
+
+            // the span of a generated group tells nothing of the tokens inside it
+            Some(_) if self.visible || tree.span().is_call_site() => match tree.kind() {
+                TokenKind::Group {
+                    open,
+                    close,
+                    stream,
+                    ..
+                } => {
+                    self.groups.push((stream.into_iter(), Some(close)));
+                    Some(bounds(&open))
+                }
+                _ => None,
+            },
+
+            _ => Some(bounds(&tree.span())),
+        }
+    }
+}
+
+impl<I, T> Iterator for Spans<I, T>
+where
+    I: Iterator,
+    I::Item: Borrow<T>,
+    T: Token,
+{
+    type Item = (LineColumn, LineColumn, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = match self.groups.last_mut() {
+                Some((stream, _)) => stream.next(),
+                None => {
+                    let tree = self.stream.next()?;
+                    if let Some(span) = self.enter(tree.borrow()) {
+                        return Some(span);
+                    }
+                    continue;
+                }
+            };
+
+            let span = match next {
+                Some(tree) => self.enter(&tree),
+                None => match self.groups.pop() {
+                    Some((_, Some(close))) => Some(bounds(&close)),
+                    _ => None,
+                },
+            };
+
+            if span.is_some() {
+                return span;
+            }
+        }
+    }
+}
+
+/// Start and end of a span, and whether it’s the call-site one.
+fn bounds(span: &impl SourceSpan) -> (LineColumn, LineColumn, bool) {
+    (span.start(), span.end(), span.is_call_site())
+}
+
+/// Whether a span is exactly one character wide, as a delimiter is.
+fn is_delimiter(span: &impl SourceSpan) -> bool {
+    let (start, end) = (span.start(), span.end());
+    start.line == end.line && start.column + 1 == end.column
+}
+
+/// Static buffers whitespaces are written from, by chunks, so that emitting them never allocates.
+const SPACES: &str = "                                                                ";
+const TABS: &str = "\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t";
+const NEWLINES: &str = "\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n";
+
+/// Whitespaces not written yet, counted rather than stored.
+#[derive(Clone, Copy, Debug, Default)]
+struct Pending {
+    /// Spaces before the first newline.
+    trailing: usize,
+    /// Newlines.
+    newlines: usize,
+    /// Columns of indentation after the last newline.
+    indent: usize,
+    /// Whether the whitespaces were chosen by the layout strategy rather than respecting the
+    /// layout of the input; their indentation is then only re-indented, not dedented.
+    laid_out: bool,
+}
+
+/// Output of the renderer, keeping track of how many bytes were written.
+///
+/// Whitespaces are counted until the next token is written, so that the whitespace options –
+/// newline style, indentation, blank lines and trailing whitespaces – can be applied to whole
+/// gaps; they are then written from static buffers. Gaps read from the source are written as they
+/// are read, a run of whitespaces at a time, so that their tabs and the whitespaces of their blank
+/// lines are kept.
+struct Output<'a, W> {
+    out: &'a mut W,
+    options: &'a FaithfulOptions,
+    len: usize,
+    /// Number of columns removed from the indentation of every line.
+    dedent: usize,
+    /// Number of columns then added to the indentation of every line.
+    reindent: usize,
+    /// Whitespaces not written yet.
+    pending: Pending,
+    /// Whether the last character written is a newline.
+    at_line_start: bool,
+}
+
+impl<'a, W> Output<'a, W>
+where
+    W: Write,
+{
+    fn new(out: &'a mut W, options: &'a FaithfulOptions) -> Self {
+        Output {
+            out,
+            options,
+            len: 0,
+            dedent: 0,
+            reindent: 0,
+            pending: Pending::default(),
+            at_line_start: false,
+        }
+    }
+
+    /// Emit `n` spaces.
+    fn spaces(&mut self, n: usize) {
+        if self.pending.newlines == 0 {
+            self.pending.trailing += n;
+        } else {
+            self.pending.indent += n;
+        }
+    }
+
+    /// Emit `n` newlines.
+    fn newlines(&mut self, n: usize) {
+        if n > 0 {
+            self.pending.newlines += n;
+            self.pending.indent = 0;
+        }
+    }
+
+    /// Emit `newlines` newlines then `spaces` spaces, as chosen by the layout strategy.
+    fn laid_out(&mut self, newlines: usize, spaces: usize) {
+        self.newlines(newlines);
+        self.spaces(spaces);
+        self.pending.laid_out = true;
+    }
+
+    /// Emit a gap read from the source, made of whitespaces and comments.
+    fn gap(&mut self, mut gap: &str) -> fmt::Result {
+        let whitespace = |c: char| matches!(c, ' ' | '\t' | '\n' | '\r');
+
+        while !gap.is_empty() {
+            let end = gap.find(|c| !whitespace(c)).unwrap_or(gap.len());
+            self.whitespaces(&gap[..end])?;
+            gap = &gap[end..];
+
+            let end = gap.find(whitespace).unwrap_or(gap.len());
+            if end > 0 {
+                self.write_str(&gap[..end])?;
+                gap = &gap[end..];
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the pending whitespaces.
+    fn flush(&mut self) -> fmt::Result {
+        let Pending {
+            trailing,
+            newlines,
+            indent,
+            laid_out,
+        } = std::mem::take(&mut self.pending);
+
+        // the smallest indentation is the one of the input, only relevant to its layout
+        let dedent = if laid_out { 0 } else { self.dedent };
+
+        if newlines == 0 {
+            // no newline: spaces before the first token, or between two tokens of the same line;
+            // the first line is only re-indented if it's anchored
+            return if self.len > 0 {
+                self.repeat(SPACES, trailing)
+            } else if self.options.first_line == FirstLine::Anchored {
+                self.columns(trailing.saturating_sub(dedent) + self.reindent)
+            } else {
+                self.columns(trailing.saturating_sub(dedent))
+            };
+        }
+
+        let options = self.options;
+        if !options.trim_trailing_whitespace {
+            self.repeat(SPACES, trailing)?;
+        }
+
+        let blank_lines = (newlines - 1).min(options.max_blank_lines.unwrap_or(usize::MAX));
+        match options.newline {
+            Newline::Lf => self.repeat(NEWLINES, blank_lines + 1)?,
+            Newline::CrLf => {
+                for _ in 0..=blank_lines {
+                    self.raw("\r\n")?;
+                }
+            }
+        }
+
+        self.columns(indent.saturating_sub(dedent) + self.reindent)
+    }
+
+    /// Write a run of whitespaces read from the source, applying the whitespace options.
+    fn whitespaces(&mut self, run: &str) -> fmt::Result {
+        // newlines are written with the chosen style
+        let mut lines = run.split('\n').map(|line| line.trim_end_matches('\r'));
+        let trailing = lines.next().unwrap_or_default();
+        let Some(indent) = lines.next_back() else {
+            return self.raw(trailing);
+        };
+
+        let options = self.options;
+        let newline = options.newline.as_str();
+
+        if !options.trim_trailing_whitespace {
+            self.raw(trailing)?;
+        }
+        self.raw(newline)?;
+
+        for blank in lines.take(options.max_blank_lines.unwrap_or(usize::MAX)) {
+            if !options.trim_trailing_whitespace {
+                self.raw(blank)?;
+            }
+            self.raw(newline)?;
+        }
+
+        // the indentation is dedented by characters, a tab standing for a single column
+        let dedent = indent
+            .char_indices()
+            .nth(self.dedent)
+            .map_or(indent.len(), |(i, _)| i);
+        let indent = &indent[dedent..];
+
+        match self.options.indent {
+            Indent::Spaces => {
+                self.repeat(SPACES, self.reindent)?;
+                self.raw(indent)
+            }
+            Indent::Tabs { .. } => self.columns(self.reindent + indent.chars().count()),
+        }
+    }
+
+    /// Write `columns` columns of indentation with the chosen characters.
+    fn columns(&mut self, columns: usize) -> fmt::Result {
+        match self.options.indent {
+            Indent::Spaces => self.repeat(SPACES, columns),
+            Indent::Tabs { width } => {
+                let width = width.max(1);
+                self.repeat(TABS, columns / width)?;
+                self.repeat(SPACES, columns % width)
+            }
+        }
+    }
+
+    /// Finish the output: a final newline is added if asked to.
+    fn finish(&mut self) -> fmt::Result {
+        self.flush()?;
+
+        if self.options.final_newline && self.len > 0 && !self.at_line_start {
+            self.raw(self.options.newline.as_str())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write `n` times the single character `buffer` is made of.
+    fn repeat(&mut self, buffer: &'static str, mut n: usize) -> fmt::Result {
+        while n > 0 {
+            let chunk = n.min(buffer.len());
+            self.raw(&buffer[..chunk])?;
+            n -= chunk;
+        }
+
+        Ok(())
+    }
+
+    /// Write `s` as is.
+    fn raw(&mut self, s: &str) -> fmt::Result {
+        if let Some(last) = s.chars().next_back() {
+            self.len += s.len();
+            self.at_line_start = last == '\n';
+            self.out.write_str(s)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W> Write for Output<'_, W>
+where
+    W: Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.flush()?;
+        self.raw(s)
+    }
+}
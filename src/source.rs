@@ -0,0 +1,180 @@
+//! Access to the source code around the tokens.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::token::{SourceSpan, Token, TokenKind};
+use crate::LineColumn;
+
+/// Byte offsets at which the lines of a text start.
+#[derive(Clone, Debug)]
+pub(crate) struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex { line_starts }
+    }
+
+    /// Byte offset of a position in `text`, if it exists.
+    ///
+    /// `text` must be the text this index was built for.
+    pub(crate) fn offset(&self, text: &str, pos: LineColumn) -> Option<usize> {
+        let start = *self.line_starts.get(pos.line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(pos.line)
+            .map_or(text.len(), |&next| next - 1);
+
+        let line = text.get(start..end)?;
+        line.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line.len()))
+            .nth(pos.column)
+            .map(|i| start + i)
+    }
+}
+
+/// Source files read so far.
+#[derive(Debug, Default)]
+pub(crate) struct Sources {
+    files: HashMap<PathBuf, Option<(String, LineIndex)>>,
+}
+
+impl Sources {
+    /// Source text between the end of `prev` and the start of `next`, if it can be found and
+    /// contains only whitespaces and comments.
+    pub(crate) fn between<S>(&mut self, prev: &S, next: &S) -> Option<String>
+    where
+        S: SourceSpan,
+    {
+        let text = self
+            .read_between(prev, next)
+            .or_else(|| joined_between(prev, next))?;
+
+        is_trivia(&text).then_some(text)
+    }
+
+    /// Read the text between two spans from their source file.
+    fn read_between<S>(&mut self, prev: &S, next: &S) -> Option<String>
+    where
+        S: SourceSpan,
+    {
+        let path = next.local_file()?;
+        if prev.local_file()? != path {
+            return None;
+        }
+
+        let (text, index) = self
+            .files
+            .entry(path)
+            .or_insert_with_key(|path| {
+                let text = fs::read_to_string(path).ok()?;
+                let index = LineIndex::new(&text);
+                Some((text, index))
+            })
+            .as_ref()?;
+
+        let start = index.offset(text, prev.end())?;
+        let end = index.offset(text, next.start())?;
+        text.get(start..end).map(str::to_owned)
+    }
+}
+
+/// Get the text between two spans by joining them, when the backend supports it.
+fn joined_between<S>(prev: &S, next: &S) -> Option<String>
+where
+    S: SourceSpan,
+{
+    let text = prev.join(next)?.source_text()?;
+    let start = prev.source_text()?.len();
+    let end = text.len().checked_sub(next.source_text()?.len())?;
+    text.get(start..end).map(str::to_owned)
+}
+
+/// Source spelling of an identifier or a literal, if it can be found.
+///
+/// The source text is only used if it looks like the token: spans that were not written by the
+/// user – call-site spans, for instance – point at unrelated text.
+pub(crate) fn spelling<T>(token: &T) -> Option<String>
+where
+    T: Token,
+{
+    let span = token.span();
+    if span.is_call_site() {
+        return None;
+    }
+
+    let text = span.source_text()?;
+    let looks_like = match token.kind() {
+        TokenKind::Ident => is_ident(&text),
+        TokenKind::Literal => {
+            let display = token.to_string();
+            text.chars().next() == display.chars().next()
+                && text.chars().next_back() == display.chars().next_back()
+        }
+        _ => false,
+    };
+
+    looks_like.then_some(text)
+}
+
+/// Whether a text is shaped as an identifier, possibly raw.
+///
+/// Non-ASCII characters are not checked further: identifiers may be written with combining marks,
+/// in decomposed form, which the compiler normalizes.
+fn is_ident(text: &str) -> bool {
+    let name = text.strip_prefix("r#").unwrap_or(text);
+    let continues =
+        |c: char| c == '_' || c.is_ascii_alphanumeric() || !c.is_ascii() && !c.is_whitespace();
+
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit()) && name.chars().all(continues)
+}
+
+/// Whether a text contains only whitespaces and comments.
+fn is_trivia(mut text: &str) -> bool {
+    loop {
+        text = text.trim_start();
+
+        if text.is_empty() {
+            return true;
+        } else if let Some(rest) = text.strip_prefix("//") {
+            text = rest.find('\n').map_or("", |i| &rest[i..]);
+        } else if let Some(rest) = text.strip_prefix("/*") {
+            match skip_block_comment(rest) {
+                Some(rest) => text = rest,
+                None => return false,
+            }
+        } else {
+            return false;
+        }
+    }
+}
+
+/// Skip the rest of a (possibly nested) block comment, returning what follows it.
+fn skip_block_comment(mut text: &str) -> Option<&str> {
+    let mut depth = 1;
+
+    while depth > 0 {
+        let i = text.find(['/', '*'])?;
+        text = &text[i..];
+
+        if text.starts_with("/*") {
+            depth += 1;
+            text = &text[2..];
+        } else if text.starts_with("*/") {
+            depth -= 1;
+            text = &text[2..];
+        } else {
+            text = &text[1..];
+        }
+    }
+
+    Some(text)
+}
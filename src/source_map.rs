@@ -0,0 +1,170 @@
+//! Mapping from a rendered output back to the spans of the tokens.
+
+use std::ops::Range;
+
+use crate::source::LineIndex;
+use crate::strategy::Lexeme;
+use crate::token::{SourceSpan, Token};
+use crate::LineColumn;
+
+/// A token emitted in the rendered output.
+#[derive(Clone, Debug)]
+pub struct Mapping<T>
+where
+    T: Token,
+{
+    /// Byte range of the token in the output.
+    pub range: Range<usize>,
+    /// Span of the token.
+    pub span: T::Span,
+    /// The token itself if it’s a literal, to compute sub-spans.
+    pub(crate) literal: Option<T>,
+    /// What the token is.
+    pub(crate) lexeme: Lexeme,
+    /// Number of visible groups enclosing the token.
+    pub(crate) depth: usize,
+}
+
+/// A span narrowed down to a part of a token.
+///
+/// Only literals can be narrowed to a span – and only if the backend supports it; the narrowed
+/// positions are always computed, though, so that diagnostics can underline the exact
+/// characters.
+#[derive(Clone, Debug)]
+pub struct Narrowed<S> {
+    /// Narrowed span if supported, span of the whole token otherwise.
+    pub span: S,
+    /// Whether [`Narrowed::span`] was actually narrowed.
+    pub exact: bool,
+    /// Position at which the narrowed part starts.
+    pub start: LineColumn,
+    /// Position at which the narrowed part ends.
+    pub end: LineColumn,
+}
+
+/// Mapping from byte offsets of a rendered output back to the spans of the tokens.
+///
+/// A [`Mapping`] is recorded for every token emitted, including delimiters. Whitespaces inserted
+/// between tokens are not mapped.
+#[derive(Clone, Debug)]
+pub struct SourceMap<T>
+where
+    T: Token,
+{
+    mappings: Vec<Mapping<T>>,
+    lines: LineIndex,
+}
+
+impl<T> SourceMap<T>
+where
+    T: Token,
+{
+    /// Build a source map out of the mappings recorded while rendering `output`.
+    pub(crate) fn new(mappings: Vec<Mapping<T>>, output: &str) -> Self {
+        SourceMap {
+            mappings,
+            lines: LineIndex::new(output),
+        }
+    }
+
+    /// All the mappings, in output order.
+    pub fn mappings(&self) -> &[Mapping<T>] {
+        &self.mappings
+    }
+
+    /// Mapping of the token containing the byte at `offset`, if any.
+    pub fn mapping_at(&self, offset: usize) -> Option<&Mapping<T>> {
+        let i = self.mappings.partition_point(|m| m.range.end <= offset);
+        self.mappings.get(i).filter(|m| m.range.start <= offset)
+    }
+
+    /// Span of the token containing the byte at `offset`, if any.
+    pub fn span_at(&self, offset: usize) -> Option<&T::Span> {
+        self.mapping_at(offset).map(|m| &m.span)
+    }
+
+    /// Mappings of the tokens overlapping a byte range.
+    ///
+    /// An empty range is considered to overlap the token it’s in; a reversed range is considered
+    /// empty.
+    pub fn mappings_for_range(&self, range: Range<usize>) -> &[Mapping<T>] {
+        let from = self
+            .mappings
+            .partition_point(|m| m.range.end <= range.start);
+        let to = self
+            .mappings
+            .partition_point(|m| m.range.start < range.end.max(range.start + 1));
+        &self.mappings[from..to.max(from)]
+    }
+
+    /// Span of the first token overlapping a byte range, if any.
+    ///
+    /// Spans cannot be joined on the stable channel; use [`SourceMap::mappings_for_range`] to get
+    /// all the tokens of the range.
+    pub fn span_for_range(&self, range: Range<usize>) -> Option<&T::Span> {
+        self.mappings_for_range(range).first().map(|m| &m.span)
+    }
+
+    /// Span of the character at `offset`, narrowed down inside its token.
+    ///
+    /// `output` must be the output this source map was built for.
+    pub fn subspan_at(&self, output: &str, offset: usize) -> Option<Narrowed<T::Span>> {
+        let len = output.get(offset..)?.chars().next()?.len_utf8();
+        self.subspan_for_range(output, offset..offset + len)
+    }
+
+    /// Span of a byte range, narrowed down inside its tokens.
+    ///
+    /// If the range spans several tokens, the positions are narrowed from the first to the last
+    /// token but the span is the one of the whole first token. `output` must be the output this
+    /// source map was built for.
+    pub fn subspan_for_range(
+        &self,
+        output: &str,
+        range: Range<usize>,
+    ) -> Option<Narrowed<T::Span>> {
+        let mappings = self.mappings_for_range(range.clone());
+        let (first, last) = (mappings.first()?, mappings.last()?);
+
+        let start = range.start.clamp(first.range.start, first.range.end);
+        let end = range.end.clamp(last.range.start, last.range.end).max(start);
+        let narrowed = match (mappings.len(), &first.literal) {
+            (1, Some(lit)) => lit.subspan(start - first.range.start..end - first.range.start),
+            _ => None,
+        };
+
+        Some(Narrowed {
+            exact: narrowed.is_some(),
+            span: narrowed.unwrap_or_else(|| first.span.clone()),
+            start: advance(first.span.start(), output.get(first.range.start..start)?),
+            end: advance(last.span.start(), output.get(last.range.start..end)?),
+        })
+    }
+
+    /// Byte offset of a line and column of the output, if it exists.
+    ///
+    /// As with any [`LineColumn`], the line is 1-indexed and the column is 0-indexed, counted in
+    /// characters.
+    pub fn offset(&self, output: &str, line_col: LineColumn) -> Option<usize> {
+        self.lines.offset(output, line_col)
+    }
+
+    /// Span of the token at a line and column of the output, if any.
+    pub fn span_at_line_column(&self, output: &str, line_col: LineColumn) -> Option<&T::Span> {
+        self.span_at(self.offset(output, line_col)?)
+    }
+}
+
+/// Position reached after `text`, starting at `pos`.
+fn advance(mut pos: LineColumn, text: &str) -> LineColumn {
+    for c in text.chars() {
+        if c == '\n' {
+            pos.line += 1;
+            pos.column = 0;
+        } else {
+            pos.column += 1;
+        }
+    }
+
+    pos
+}
@@ -0,0 +1,276 @@
+//! Layout strategies, choosing the whitespaces emitted between tokens.
+//!
+//! The renderer consults a [`LayoutStrategy`] before every token and delimiter, describing the
+//! [`Gap`] to fill. The built-in strategies are:
+//!
+//! - [`Faithful`], respecting the layout of the input – the default;
+//! - [`Compact`], emitting as few whitespaces as possible;
+//! - [`Pretty`], laying out the tokens with a canonical indentation.
+
+use std::fmt::Debug;
+
+use crate::token::{Delimiter, Spacing};
+use crate::LineColumn;
+
+/// A token – or a delimiter –, as far as separating it from its neighbours is concerned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lexeme {
+    /// An identifier.
+    Ident,
+    /// A literal.
+    Literal,
+    /// A punctuation.
+    Punct {
+        /// The punctuation character.
+        ch: char,
+        /// Whether the punctuation is immediately followed by another one.
+        spacing: Spacing,
+        /// Whether the punctuation follows a joint punctuation, as the second `:` of `::`.
+        joined: bool,
+    },
+    /// An opening delimiter.
+    Open(Delimiter),
+    /// A closing delimiter.
+    Close(Delimiter),
+    /// A doc comment.
+    Comment {
+        /// Whether the comment spans the rest of its line, as `///` comments do.
+        line: bool,
+    },
+}
+
+impl Lexeme {
+    /// Whether the lexeme is an identifier or a literal.
+    pub fn is_word(self) -> bool {
+        matches!(self, Lexeme::Ident | Lexeme::Literal)
+    }
+}
+
+/// The gap between two tokens, in which whitespaces are emitted.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Gap {
+    /// Last emitted token, if any.
+    pub last: Option<Lexeme>,
+    /// Next token.
+    pub next: Lexeme,
+    /// Number of groups enclosing the next token.
+    ///
+    /// The delimiters of a group are not enclosed by the group itself.
+    pub depth: usize,
+    /// Innermost delimiter enclosing the next token, if any.
+    pub delimiter: Option<Delimiter>,
+    /// Position at which the last token ended in the input.
+    pub from: LineColumn,
+    /// Position at which the next token starts in the input.
+    pub to: LineColumn,
+    /// Whether the layout of the next token can be respected: it starts after the last token and
+    /// has a non-empty span.
+    pub in_order: bool,
+}
+
+/// Whitespaces to emit in a [`Gap`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Whitespace {
+    /// Respect the layout of the input, applying the [`SpanFallback`] if the layout of the next
+    /// token cannot be respected, and preserving comments if asked to.
+    ///
+    /// [`SpanFallback`]: crate::SpanFallback
+    Input,
+    /// Emit exactly these whitespaces.
+    ///
+    /// At least one newline is emitted after a `///` or `//!` comment, whatever the number asked
+    /// for.
+    Exactly {
+        /// Number of newlines.
+        newlines: usize,
+        /// Number of spaces, after the newlines.
+        spaces: usize,
+    },
+}
+
+impl Whitespace {
+    /// No whitespace at all.
+    pub const NONE: Whitespace = Whitespace::Exactly {
+        newlines: 0,
+        spaces: 0,
+    };
+
+    /// A single space.
+    pub const SPACE: Whitespace = Whitespace::Exactly {
+        newlines: 0,
+        spaces: 1,
+    };
+
+    /// A newline, followed by `indent` spaces.
+    pub fn line(indent: usize) -> Whitespace {
+        Whitespace::Exactly {
+            newlines: 1,
+            spaces: indent,
+        }
+    }
+}
+
+/// A way to lay out tokens.
+///
+/// Strategies are consulted before every token and delimiter – including the first one – to
+/// choose the whitespaces to emit.
+pub trait LayoutStrategy: Debug {
+    /// Whitespaces to emit in `gap`.
+    fn whitespace(&self, gap: &Gap) -> Whitespace;
+}
+
+/// Respect the layout of the input.
+///
+/// This is the default strategy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Faithful;
+
+impl LayoutStrategy for Faithful {
+    fn whitespace(&self, _: &Gap) -> Whitespace {
+        Whitespace::Input
+    }
+}
+
+/// Emit as few whitespaces as possible, so that the output still lexes to the same tokens.
+///
+/// A single space is emitted where two tokens would merge – as two identifiers would – or where a
+/// punctuation would become joint with the next one; nothing is emitted otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Compact;
+
+impl LayoutStrategy for Compact {
+    fn whitespace(&self, gap: &Gap) -> Whitespace {
+        match gap.last {
+            Some(last) if must_separate(last, gap.next) => Whitespace::SPACE,
+            _ => Whitespace::NONE,
+        }
+    }
+}
+
+/// Lay out the tokens with a canonical indentation, whatever their layout in the input.
+///
+/// Blocks are split into one statement – or field – per line, indented by their nesting depth;
+/// tokens on the same line are separated as with [`SpanFallback::Heuristic`].
+///
+/// [`SpanFallback::Heuristic`]: crate::SpanFallback::Heuristic
+#[derive(Clone, Copy, Debug)]
+pub struct Pretty {
+    /// Number of spaces per indentation level.
+    pub indent: usize,
+}
+
+impl Default for Pretty {
+    fn default() -> Self {
+        Pretty { indent: 4 }
+    }
+}
+
+impl LayoutStrategy for Pretty {
+    fn whitespace(&self, gap: &Gap) -> Whitespace {
+        let Some(last) = gap.last else {
+            return Whitespace::NONE;
+        };
+
+        let block = matches!(gap.delimiter, None | Some(Delimiter::Brace));
+        let indent = self.indent * gap.depth;
+
+        match (last, gap.next) {
+            (Lexeme::Open(Delimiter::Brace), Lexeme::Close(Delimiter::Brace)) => Whitespace::NONE,
+            (Lexeme::Open(Delimiter::Brace), _) => Whitespace::line(indent),
+            (_, Lexeme::Close(Delimiter::Brace)) => Whitespace::line(indent),
+            (Lexeme::Comment { .. }, _) => Whitespace::line(indent),
+            (Lexeme::Punct { ch: ';', .. }, _) if block => Whitespace::line(indent),
+            (Lexeme::Punct { ch: ',', .. }, _) if gap.delimiter == Some(Delimiter::Brace) => {
+                Whitespace::line(indent)
+            }
+            (
+                Lexeme::Close(Delimiter::Brace),
+                Lexeme::Ident | Lexeme::Literal | Lexeme::Comment { .. },
+            ) if block => Whitespace::line(indent),
+            (last, next) if separated(last, next) => Whitespace::SPACE,
+            _ => Whitespace::NONE,
+        }
+    }
+}
+
+/// Whether two tokens which layout is unknown are separated by a space with
+/// [`SpanFallback::Heuristic`].
+///
+/// Identifiers and literals are always separated, so that they don’t merge; other tokens are
+/// mostly laid out as rustfmt would, using the [`Spacing`] of punctuations.
+///
+/// [`SpanFallback::Heuristic`]: crate::SpanFallback::Heuristic
+pub(crate) fn separated(last: Lexeme, next: Lexeme) -> bool {
+    use Lexeme::*;
+
+    if must_separate(last, next) {
+        return true;
+    }
+
+    match (last, next) {
+        // joint punctuations are glued to what follows, as in `+=`, `::` or `'a`
+        (
+            Punct {
+                spacing: Spacing::Joint,
+                ..
+            },
+            _,
+        ) => false,
+        (Open(Delimiter::Brace), Close(Delimiter::Brace)) => false,
+        (Open(Delimiter::Brace), _) | (_, Close(Delimiter::Brace)) => true,
+        (Open(_), _) | (_, Close(_)) => false,
+        // `::`, `.` and prefix operators are glued to what follows
+        (
+            Punct {
+                ch: ':',
+                joined: true,
+                ..
+            }
+            | Punct {
+                ch: '.' | '#' | '$' | '!' | '&',
+                joined: false,
+                ..
+            },
+            _,
+        ) => false,
+        (
+            _,
+            Punct {
+                ch: ',' | ';' | ':' | '.' | '?',
+                ..
+            },
+        ) => false,
+        // macro calls, function calls and indexing
+        (Ident, Punct { ch: '!', .. }) => false,
+        (Ident | Literal | Close(_), Open(Delimiter::Parenthesis | Delimiter::Bracket)) => false,
+        _ => true,
+    }
+}
+
+/// Whether two tokens must be separated by a whitespace so that they lex back to the same tokens.
+///
+/// Literals are not known precisely: a literal followed by a `.` is always separated from it, in
+/// case the literal is an integer that would become a floating-point number.
+pub(crate) fn must_separate(last: Lexeme, next: Lexeme) -> bool {
+    use Lexeme::*;
+
+    match (last, next) {
+        // identifiers and literals would merge, and `//` or `/*` would start a comment
+        (Ident | Literal, Ident | Literal) => true,
+        (Punct { ch: '/', .. }, Punct { ch: '/' | '*', .. } | Comment { .. }) => true,
+        // a punctuation that is not joint would become so
+        (
+            Punct {
+                spacing: Spacing::Alone,
+                ..
+            },
+            Punct { .. },
+        ) => true,
+        // `1.0` is a single literal, and `r#`, `b'` or `foo"` are literal or reserved prefixes
+        (Literal, Punct { ch: '.', .. }) => true,
+        (Ident, Punct { ch: '#' | '\'', .. }) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,150 @@
+//! Backend-agnostic token model.
+//!
+//! The rendering logic doesn’t know anything about [proc_macro] or [proc-macro2]: it only sees
+//! [`Token`]s, which are either leaves with a start and end position and a textual form, or groups
+//! with the positions of their opening and closing delimiters. Each backend implements [`Token`]
+//! for its token tree type, and you can implement it for your own types as well, for instance to
+//! build synthetic tokens in tests.
+//!
+//! [proc-macro2]: https://crates.io/crates/proc-macro2
+
+use std::fmt::{Debug, Display};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::{LineColumn, Region};
+
+/// A span with line and column information.
+pub trait SourceSpan: Clone + Debug {
+    /// Position at which the span starts.
+    fn start(&self) -> LineColumn;
+
+    /// Position at which the span ends.
+    fn end(&self) -> LineColumn;
+
+    /// Region of the source the span covers, which – unlike the span – can be kept around.
+    fn region(&self) -> Region {
+        Region::of(self)
+    }
+
+    /// Path of the source file the span comes from, if known.
+    fn file(&self) -> Option<String> {
+        None
+    }
+
+    /// Path of the source file on disk the span comes from, if known.
+    fn local_file(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Source text behind the span, if known.
+    fn source_text(&self) -> Option<String> {
+        None
+    }
+
+    /// Span covering both this span and `other`, if supported.
+    fn join(&self, other: &Self) -> Option<Self> {
+        let _ = other;
+        None
+    }
+
+    /// Whether the span is the call-site span of the macro invocation rather than a span of the
+    /// input.
+    fn is_call_site(&self) -> bool {
+        false
+    }
+}
+
+/// Delimiter of a [`TokenKind::Group`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Delimiter {
+    /// `( … )`
+    Parenthesis,
+    /// `{ … }`
+    Brace,
+    /// `[ … ]`
+    Bracket,
+    /// Invisible delimiters.
+    None,
+}
+
+impl Delimiter {
+    /// Opening and closing characters of the delimiter, if visible.
+    pub fn chars(self) -> Option<(char, char)> {
+        match self {
+            Delimiter::Parenthesis => Some(('(', ')')),
+            Delimiter::Brace => Some(('{', '}')),
+            Delimiter::Bracket => Some(('[', ']')),
+            Delimiter::None => None,
+        }
+    }
+}
+
+/// Whether a punctuation is immediately followed by another one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Spacing {
+    /// Followed by something else than a punctuation, or by whitespaces.
+    Alone,
+    /// Immediately followed by another punctuation, as the `=` of `+=` or the first `:` of `::`.
+    Joint,
+}
+
+/// What a [`Token`] is made of.
+#[derive(Clone, Debug)]
+pub enum TokenKind<S, T> {
+    /// An identifier.
+    Ident,
+    /// A single punctuation character, and whether it’s immediately followed by another one.
+    Punct(char, Spacing),
+    /// A literal (string, number, etc.).
+    Literal,
+    /// A delimited group of tokens.
+    Group {
+        /// Delimiter of the group.
+        delimiter: Delimiter,
+        /// Span of the opening delimiter.
+        open: S,
+        /// Span of the closing delimiter.
+        close: S,
+        /// Tokens inside the group.
+        stream: T,
+    },
+}
+
+/// A token the renderer can display faithfully.
+///
+/// Tokens are cloned as they are traversed, so cloning should be cheap. The [`Display`]
+/// implementation is used for the textual form of leaves (identifiers, punctuations and
+/// literals); it is never called on groups.
+pub trait Token: Clone + Display {
+    /// Span type of the token.
+    type Span: SourceSpan;
+
+    /// Tokens inside a group.
+    type Stream: IntoIterator<Item = Self>;
+
+    /// Span of the whole token.
+    fn span(&self) -> Self::Span;
+
+    /// Kind of the token.
+    fn kind(&self) -> TokenKind<Self::Span, Self::Stream>;
+
+    /// Delimiter of the token if it’s a group.
+    ///
+    /// Getting the [`TokenKind`] of a group clones its stream; backends should override this
+    /// method to get the delimiter without doing so.
+    fn delimiter(&self) -> Option<Delimiter> {
+        match self.kind() {
+            TokenKind::Group { delimiter, .. } => Some(delimiter),
+            _ => None,
+        }
+    }
+
+    /// Span of a byte range of the textual form of a literal, if supported.
+    fn subspan(&self, range: Range<usize>) -> Option<Self::Span> {
+        let _ = range;
+        None
+    }
+}
@@ -0,0 +1,160 @@
+//! Tokens annotated with the whitespaces preceding them, for parsers working on tokens.
+
+use crate::render::advances;
+use crate::token::{Delimiter, SourceSpan, Token, TokenKind};
+use crate::{LineColumn, Region};
+
+/// Whitespaces preceding a token in the input.
+///
+/// Whitespaces are computed from the spans of the tokens, as the renderer does: comments between
+/// two tokens count as whitespaces, and tabs as single columns.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Trivia {
+    /// Number of newlines since the previous token.
+    pub newlines: usize,
+    /// Number of spaces since the previous token if on the same line; column of the token
+    /// otherwise.
+    pub spaces: usize,
+    /// Whether the token immediately follows the previous one, as `b` does in `a.b`.
+    pub adjacent: bool,
+    /// Whether the layout of the token is known: it starts after the previous token and has a
+    /// non-empty span. If not, there are no whitespaces and the token is not adjacent.
+    pub in_order: bool,
+}
+
+impl Trivia {
+    /// Trivia preceding `span`, the previous token having ended at `prev`, if any.
+    fn before(prev: Option<LineColumn>, span: &impl SourceSpan) -> Self {
+        let (start, end) = (span.start(), span.end());
+
+        let Some(prev) = prev else {
+            return Trivia {
+                in_order: true,
+                ..Trivia::default()
+            };
+        };
+
+        match prev.distance_to(start) {
+            Some(distance) if advances(prev, start, end, false) => Trivia {
+                newlines: distance.lines,
+                spaces: distance.columns,
+                adjacent: distance.lines == 0 && distance.columns == 0,
+                in_order: true,
+            },
+            _ => Trivia::default(),
+        }
+    }
+}
+
+/// Iterator over the tokens of a stream, each with its leading [`Trivia`].
+///
+/// Invisible groups are transparent: the tokens inside them are yielded instead. The tokens inside
+/// visible groups are not: they are iterated over with [`TriviaTokens::inside`].
+///
+/// This is the iterator returned by [`tokens_with_trivia`].
+///
+/// [`tokens_with_trivia`]: crate::tokens_with_trivia
+pub struct TriviaTokens<I, T>
+where
+    T: Token,
+{
+    stream: I,
+    /// Streams of the invisible groups being looked inside.
+    groups: Vec<<T::Stream as IntoIterator>::IntoIter>,
+    /// Position at which the previous token ended, if any.
+    prev: Option<LineColumn>,
+    /// Region of the closing delimiter, when iterating inside a group.
+    close: Option<Region>,
+}
+
+impl<I, T> TriviaTokens<I, T>
+where
+    I: Iterator<Item = T>,
+    T: Token,
+{
+    pub(crate) fn new(stream: I) -> Self {
+        TriviaTokens {
+            stream,
+            groups: Vec::new(),
+            prev: None,
+            close: None,
+        }
+    }
+
+    /// Trivia preceding the closing delimiter, once all the tokens inside a group were yielded.
+    ///
+    /// This is `None` if the iterator is not over the tokens of a visible group.
+    pub fn trailing(&self) -> Option<Trivia> {
+        let close = self.close?;
+        Some(Trivia::before(self.prev, &close))
+    }
+}
+
+impl<T> TriviaTokens<<T::Stream as IntoIterator>::IntoIter, T>
+where
+    T: Token,
+{
+    /// Iterate over the tokens inside a group, if `group` is one.
+    ///
+    /// The trivia of the first token is the whitespaces following the opening delimiter.
+    pub fn inside(group: &T) -> Option<Self> {
+        let TokenKind::Group {
+            delimiter,
+            open,
+            close,
+            stream,
+        } = group.kind()
+        else {
+            return None;
+        };
+
+        let visible = delimiter != Delimiter::None;
+        Some(TriviaTokens {
+            stream: stream.into_iter(),
+            groups: Vec::new(),
+            prev: visible.then(|| open.end()),
+            close: visible.then(|| close.region()),
+        })
+    }
+}
+
+impl<I, T> Iterator for TriviaTokens<I, T>
+where
+    I: Iterator<Item = T>,
+    T: Token,
+{
+    type Item = (T, Trivia);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let tree = match self.groups.last_mut() {
+                Some(stream) => match stream.next() {
+                    Some(tree) => tree,
+                    None => {
+                        self.groups.pop();
+                        continue;
+                    }
+                },
+                None => self.stream.next()?,
+            };
+
+            if tree.delimiter() == Some(Delimiter::None) {
+                if let TokenKind::Group { stream, .. } = tree.kind() {
+                    self.groups.push(stream.into_iter());
+                }
+                continue;
+            }
+
+            let span = tree.span();
+            let trivia = Trivia::before(self.prev, &span);
+
+            // as when rendering, tokens which layout is unknown don’t move the position
+            if trivia.in_order {
+                self.prev = Some(span.end());
+            }
+
+            return Some((tree, trivia));
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Adapters to write the rendering to other outputs than [`fmt::Write`] implementors.
+
+use std::fmt;
+use std::io;
+
+/// [`fmt::Write`] adapter over an [`io::Write`], keeping the I/O error that made writing fail.
+pub(crate) struct IoWriter<'a, W> {
+    out: &'a mut W,
+    pub(crate) error: Option<io::Error>,
+}
+
+impl<'a, W> IoWriter<'a, W> {
+    pub(crate) fn new(out: &'a mut W) -> Self {
+        IoWriter { out, error: None }
+    }
+}
+
+impl<W> fmt::Write for IoWriter<'_, W>
+where
+    W: io::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
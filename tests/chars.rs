@@ -0,0 +1,61 @@
+//! Characters of a rendering, each with the span it comes from.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::TokenStream;
+use proc_macro_faithful_display::token::SourceSpan;
+use proc_macro_faithful_display::{faithful_chars, faithful_to_string, CharOrigin, LineColumn};
+
+#[test]
+fn chars_make_the_rendering() {
+    let stream: TokenStream = "fn f() {\n    g(1, \"two\")\n}".parse().unwrap();
+    let text: String = faithful_chars(&stream).map(|c| c.ch).collect();
+
+    assert_eq!(text, faithful_to_string(&stream));
+}
+
+#[test]
+fn chars_of_tokens() {
+    let stream: TokenStream = "a  +\n  bc".parse().unwrap();
+    let chars: Vec<_> = faithful_chars(&stream).collect();
+
+    let c = &chars[8];
+    assert_eq!(c.ch, 'c');
+    assert_eq!(c.offset, 8);
+
+    let CharOrigin::Token(narrowed) = &c.origin else {
+        panic!("`c` is not in a token: {:?}", c.origin);
+    };
+    assert_eq!(narrowed.start, LineColumn::new(2, 3));
+    assert_eq!(narrowed.end, LineColumn::new(2, 4));
+    assert_eq!(narrowed.span.region().start, LineColumn::new(2, 2));
+}
+
+#[test]
+fn chars_of_gaps() {
+    let stream: TokenStream = "a  +\n  bc".parse().unwrap();
+    let chars: Vec<_> = faithful_chars(&stream).collect();
+
+    for c in &chars[4..7] {
+        let CharOrigin::Gap { prev, next } = &c.origin else {
+            panic!("{:?} is not in a gap: {:?}", c.ch, c.origin);
+        };
+        assert_eq!(prev.unwrap().region().start, LineColumn::new(1, 3));
+        assert_eq!(next.unwrap().region().start, LineColumn::new(2, 2));
+    }
+
+    assert!(chars[1].span().is_none());
+}
+
+#[test]
+fn chars_of_literals() {
+    let stream: TokenStream = "x = \"abc\"".parse().unwrap();
+    let chars: Vec<_> = faithful_chars(&stream).collect();
+
+    let CharOrigin::Token(narrowed) = &chars[6].origin else {
+        panic!("`b` is not in a token");
+    };
+    assert!(narrowed.exact);
+    assert_eq!(narrowed.span.region().start, LineColumn::new(1, 6));
+    assert_eq!(narrowed.span.region().end, LineColumn::new(1, 7));
+}
@@ -0,0 +1,68 @@
+//! Compact displays, which must lex back to the same tokens.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{TokenStream, TokenTree};
+use proc_macro_faithful_display::compact_display;
+
+/// Assert that two streams are made of the same tokens, with the same spacing and delimiters.
+fn assert_same_tokens(left: TokenStream, right: TokenStream) {
+    let left: Vec<TokenTree> = left.into_iter().collect();
+    let right: Vec<TokenTree> = right.into_iter().collect();
+    assert_eq!(left.len(), right.len(), "{left:?} != {right:?}");
+
+    for (left, right) in left.into_iter().zip(right) {
+        match (left, right) {
+            (TokenTree::Group(left), TokenTree::Group(right)) => {
+                assert_eq!(left.delimiter(), right.delimiter());
+                assert_same_tokens(left.stream(), right.stream());
+            }
+
+            (TokenTree::Punct(left), TokenTree::Punct(right)) => {
+                assert_eq!(left.as_char(), right.as_char());
+                assert_eq!(left.spacing(), right.spacing());
+            }
+
+            (TokenTree::Ident(left), TokenTree::Ident(right)) => assert_eq!(left, right),
+            (TokenTree::Literal(left), TokenTree::Literal(right)) => {
+                assert_eq!(left.to_string(), right.to_string());
+            }
+
+            (left, right) => panic!("{left:?} != {right:?}"),
+        }
+    }
+}
+
+/// Display `source` compactly, and check that the output lexes back to the same tokens.
+fn round_trip(source: &str) -> String {
+    let stream: TokenStream = source.parse().unwrap();
+    let output = compact_display(&stream).to_string();
+
+    assert_same_tokens(output.parse().unwrap(), stream);
+    output
+}
+
+#[test]
+fn compact_operators() {
+    assert_eq!(round_trip("a - -b"), "a- -b");
+    assert_eq!(round_trip("a / / b"), "a/ /b");
+    assert_eq!(round_trip("x += 1 ;"), "x+=1;");
+}
+
+#[test]
+fn compact_literals() {
+    assert_eq!(round_trip("1 . foo"), "1 .foo");
+    assert_eq!(round_trip("b \"s\""), "b \"s\"");
+    assert_eq!(round_trip("r#type x"), "r#type x");
+}
+
+#[test]
+fn compact_lifetimes() {
+    assert_eq!(round_trip("'a: loop {}"), "'a:loop{}");
+}
+
+#[test]
+fn compact_doc_comments() {
+    assert_eq!(round_trip("/// Doc.\nfn f() {}"), "/// Doc.\nfn f(){}");
+    round_trip("//! Inner.\n/// Outer.\n#[inline] fn f() {}");
+}
@@ -0,0 +1,66 @@
+//! Rendering of deeply nested groups, as found in generated data embedded in macro invocations.
+
+#![cfg(feature = "proc-macro2")]
+
+use std::mem::ManuallyDrop;
+use std::thread;
+
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::{faithful_to_string, FaithfulError, FaithfulOptions};
+
+/// Build `a [a [… a []…]]`, `depth` groups deep, without recursing.
+///
+/// The stream is never dropped by the tests, since dropping it recurses.
+fn nested(depth: usize) -> ManuallyDrop<TokenStream> {
+    let mut stream = TokenStream::new();
+
+    for _ in 0..depth {
+        let ident = Ident::new("a", Span::call_site());
+        let group = Group::new(Delimiter::Bracket, stream);
+        stream = [TokenTree::Ident(ident), TokenTree::Group(group)]
+            .into_iter()
+            .collect();
+    }
+
+    ManuallyDrop::new(stream)
+}
+
+#[test]
+fn deep_nesting_does_not_overflow() {
+    let depth = 100_000;
+
+    // a small stack, which recursing once per group would overflow
+    let output = thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || faithful_to_string(&*nested(depth)))
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let expected = "a [ ".repeat(depth) + "] ".repeat(depth).trim_end();
+    assert!(output == expected);
+}
+
+#[test]
+fn max_depth() {
+    let stream: TokenStream = "a [b (c)] {d}".parse().unwrap();
+
+    let error = FaithfulOptions::new()
+        .max_depth(1)
+        .try_string(&stream)
+        .unwrap_err();
+    assert!(matches!(error, FaithfulError::TooDeep(_)));
+    assert_eq!(error.span().unwrap().start().column, 5);
+
+    let output = FaithfulOptions::new().max_depth(2).try_string(&stream);
+    assert_eq!(output.unwrap(), "a [b (c)] {d}");
+}
+
+#[test]
+fn max_depth_counts_invisible_groups() {
+    let group = Group::new(Delimiter::None, "x".parse().unwrap());
+    let stream: TokenStream = TokenTree::Group(group).into();
+
+    let output = FaithfulOptions::new().max_depth(0).try_string(&stream);
+    assert!(matches!(output, Err(FaithfulError::TooDeep(_))));
+}
@@ -0,0 +1,84 @@
+//! Rendering of invisible groups, as produced by `macro_rules!` when forwarding `$x:expr`
+//! captures.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::{faithful_display, FaithfulOptions, SpanFallback};
+
+/// Wrap the tokens `range` of `stream` into an invisible group, as forwarding them as an
+/// `$x:expr` would.
+fn forward(stream: TokenStream, range: std::ops::Range<usize>) -> TokenStream {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    let captured = tokens[range.clone()].iter().cloned().collect();
+
+    let mut group = Group::new(Delimiter::None, captured);
+    group.set_span(Span::call_site());
+
+    let mut forwarded = tokens[..range.start].to_vec();
+    forwarded.push(group.into());
+    forwarded.extend_from_slice(&tokens[range.end..]);
+    forwarded.into_iter().collect()
+}
+
+#[test]
+fn forwarded_expr() {
+    let source = "let y = foo(a + b) * 2;";
+    let stream = forward(source.parse().unwrap(), 3..5);
+
+    assert_eq!(faithful_display(&stream).to_string(), source);
+}
+
+#[test]
+fn nested_forwarded_expr() {
+    let source = "let y =   foo(a + b,\n    c)\n    * 2;";
+    let once = forward(source.parse().unwrap(), 3..7);
+    let twice = forward(once.clone(), 3..4);
+    let thrice = forward(twice.clone(), 2..5);
+
+    assert_eq!(faithful_display(&once).to_string(), source);
+    assert_eq!(faithful_display(&twice).to_string(), source);
+    assert_eq!(faithful_display(&thrice).to_string(), source);
+}
+
+#[test]
+fn forwarded_expr_at_the_edges() {
+    let source = "\n  a +\n  b";
+    let stream = forward(source.parse().unwrap(), 0..3);
+    let stream = forward(stream, 0..1);
+
+    // no leading whitespace is emitted before the first token
+    assert_eq!(faithful_display(&stream).to_string(), "a +\n  b");
+}
+
+#[test]
+fn empty_forwarded_expr() {
+    let source = "f(x)   . g";
+    let mut tokens: Vec<TokenTree> = source.parse::<TokenStream>().unwrap().into_iter().collect();
+    tokens.insert(2, Group::new(Delimiter::None, TokenStream::new()).into());
+    let stream: TokenStream = tokens.into_iter().collect();
+
+    assert_eq!(faithful_display(&stream).to_string(), source);
+}
+
+#[test]
+fn forwarded_expr_is_strict() {
+    let source = "x = (a, b)\n  .0;";
+    let stream = forward(source.parse().unwrap(), 2..4);
+    let stream = forward(stream, 2..3);
+
+    // the call-site spans of the invisible groups are never used
+    let options = FaithfulOptions::new().fallback(SpanFallback::Strict);
+    assert_eq!(options.try_string(&stream).unwrap(), source);
+}
+
+#[test]
+fn forwarded_expr_is_mapped() {
+    let source = "f(a,  b)";
+    let stream = forward(source.parse().unwrap(), 1..2);
+    let (output, map) = FaithfulOptions::new().string_with_map(&stream);
+
+    assert_eq!(output, source);
+    let b = output.find('b').unwrap();
+    assert_eq!(map.mapping_at(b).unwrap().range, b..b + 1);
+}
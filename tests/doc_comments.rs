@@ -0,0 +1,85 @@
+//! Doc comments, displayed back as comments rather than as `#[doc = "…"]` attributes.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Group, Literal, TokenStream, TokenTree};
+use proc_macro_faithful_display::faithful_to_string;
+
+/// Replace the literal of the doc comment starting `source` by `lit`, as if the comment was
+/// generated rather than read from the source.
+fn with_literal(source: &str, lit: Literal) -> String {
+    let mut tokens: Vec<TokenTree> = source.parse::<TokenStream>().unwrap().into_iter().collect();
+    let i = tokens
+        .iter()
+        .position(|tree| matches!(tree, TokenTree::Group(_)))
+        .unwrap();
+    let TokenTree::Group(group) = &tokens[i] else {
+        unreachable!();
+    };
+
+    let mut attribute: Vec<TokenTree> = group.stream().into_iter().collect();
+    attribute[2] = lit.into();
+
+    let mut rebuilt = Group::new(group.delimiter(), attribute.into_iter().collect());
+    rebuilt.set_span(group.span());
+    tokens[i] = rebuilt.into();
+
+    faithful_to_string(&tokens.into_iter().collect::<TokenStream>())
+}
+
+#[test]
+fn line_doc_comments() {
+    let source = "/// Doc.\n///\n///  Indented.\nfn f() {}";
+    let stream: TokenStream = source.parse().unwrap();
+    assert_eq!(faithful_to_string(&stream), source);
+
+    let source = "mod m {\n    //! Inner.\n    fn f() {}\n}";
+    let stream: TokenStream = source.parse().unwrap();
+    assert_eq!(faithful_to_string(&stream), source);
+}
+
+#[test]
+fn block_doc_comments() {
+    let source = "/** Block\n *  doc.\n */\nfn f() {}";
+    let stream: TokenStream = source.parse().unwrap();
+    assert_eq!(faithful_to_string(&stream), source);
+
+    let source = "mod m { /*! Inner. */ }";
+    let stream: TokenStream = source.parse().unwrap();
+    assert_eq!(faithful_to_string(&stream), source);
+}
+
+#[test]
+fn doc_comments_from_escaped_literals() {
+    let lit = Literal::string(" \"Quoted\"\tand \\escaped\\ é.");
+    let output = with_literal("/// Doc.\nfn f() {}", lit);
+    assert_eq!(output, "/// \"Quoted\"\tand \\escaped\\ é.\nfn f() {}");
+
+    let lit = Literal::string(" First line.\n Second line.\n");
+    let output = with_literal("/// Doc.\nfn f() {}", lit);
+    assert_eq!(output, "/** First line.\n Second line.\n*/\nfn f() {}");
+
+    let lit = Literal::string(" Inner.");
+    let output = with_literal("//! Doc.\nfn f() {}", lit);
+    assert_eq!(output, "//! Inner.\nfn f() {}");
+
+    let lit: Literal = "r#\" Raw \"doc\".\"#".parse().unwrap();
+    let output = with_literal("/// Doc.\nfn f() {}", lit);
+    assert_eq!(output, "/// Raw \"doc\".\nfn f() {}");
+
+    let lit: Literal = "\" Unicode \\u{e9}, hex \\x41.\"".parse().unwrap();
+    let output = with_literal("/// Doc.\nfn f() {}", lit);
+    assert_eq!(output, "/// Unicode é, hex A.\nfn f() {}");
+}
+
+#[test]
+fn hand_written_doc_attributes() {
+    for source in [
+        "#[doc = \"x\"]\nfn f() {}",
+        "#![doc = \"x\"]",
+        "#[doc = \"x\"] #[doc(hidden)] fn f() {}",
+    ] {
+        let stream: TokenStream = source.parse().unwrap();
+        assert_eq!(faithful_to_string(&stream), source);
+    }
+}
@@ -0,0 +1,72 @@
+//! Errors of the strict rendering, carrying the span of the offending token.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::token::SourceSpan;
+use proc_macro_faithful_display::{try_faithful_string, FaithfulError, LineColumn, Region};
+
+/// Tokens of `source`, as written by the user.
+fn input(source: &str) -> Vec<TokenTree> {
+    let stream: TokenStream = source.parse().unwrap();
+    stream.into_iter().collect()
+}
+
+#[test]
+fn clean_input() {
+    let source = "fn f(x: u32) -> u32 {\n    x + 1\n}";
+    let stream: TokenStream = source.parse().unwrap();
+
+    assert_eq!(try_faithful_string(&stream).unwrap(), source);
+    assert_eq!(try_faithful_string(&TokenStream::new()).unwrap(), "");
+}
+
+#[test]
+fn call_site_token() {
+    let mut tokens = input("a b");
+    tokens.push(Ident::new("c", Span::call_site()).into());
+    let stream: TokenStream = tokens.into_iter().collect();
+
+    let error = try_faithful_string(&stream).unwrap_err();
+    assert!(matches!(error, FaithfulError::CallSite(_)));
+    assert!(error.span().unwrap().is_call_site());
+}
+
+#[test]
+fn token_from_another_file() {
+    let a = input("a");
+    let b = input("  b");
+    let stream: TokenStream = a.into_iter().chain(b.iter().cloned()).collect();
+
+    let error = try_faithful_string(&stream).unwrap_err();
+    assert!(matches!(error, FaithfulError::DifferentFile(_)));
+
+    let span = error.span().unwrap();
+    assert_eq!(span.file(), b[0].span().file());
+    assert_eq!(
+        span.region(),
+        Region::new(LineColumn::new(1, 2), LineColumn::new(1, 3))
+    );
+}
+
+#[test]
+fn group_without_delimiter_spans() {
+    let mut tokens = input("a \"bc\"");
+    let TokenTree::Literal(lit) = tokens.pop().unwrap() else {
+        unreachable!();
+    };
+
+    // an empty span, as generated groups may have, cannot be the span of delimiters
+    let span = lit.subspan(1..1).unwrap();
+    let mut group = Group::new(Delimiter::Parenthesis, TokenStream::new());
+    group.set_span(span);
+    tokens.push(group.into());
+    let stream: TokenStream = tokens.into_iter().collect();
+
+    let error = try_faithful_string(&stream).unwrap_err();
+    assert!(matches!(error, FaithfulError::MissingDelimiterSpan(_)));
+    assert_eq!(
+        error.span().unwrap().region(),
+        Region::new(LineColumn::new(1, 3), LineColumn::new(1, 3))
+    );
+}
@@ -0,0 +1,161 @@
+//! Separation of tokens which layout is unknown, as generated tokens are.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::token::SourceSpan;
+use proc_macro_faithful_display::{
+    FaithfulError, FaithfulLayout, FaithfulOptions, LineColumn, SpanFallback,
+};
+
+/// Tokens of `source` with the call-site span, as `quote!` generates them.
+fn generated(source: &str) -> Vec<TokenTree> {
+    let stream: TokenStream = source.parse().unwrap();
+
+    stream
+        .into_iter()
+        .map(|mut tree| {
+            tree.set_span(Span::call_site());
+            tree
+        })
+        .collect()
+}
+
+/// Tokens of `source`, as written by the user.
+fn input(source: &str) -> Vec<TokenTree> {
+    let stream: TokenStream = source.parse().unwrap();
+    stream.into_iter().collect()
+}
+
+/// Display `parts` one after the other with the heuristic fallback.
+fn heuristic(parts: &[Vec<TokenTree>]) -> String {
+    let stream: TokenStream = parts.iter().flatten().cloned().collect();
+    let options = FaithfulOptions::new().fallback(SpanFallback::Heuristic);
+    let output = options.string(&stream);
+
+    // layouts are displayed the same way
+    let layout = FaithfulLayout::compute(&stream);
+    assert_eq!(layout.display_with(options).to_string(), output);
+
+    output
+}
+
+#[test]
+fn heuristic_path() {
+    let output = heuristic(&[generated("std::"), input("foo()")]);
+    assert_eq!(output, "std::foo()");
+
+    let output = heuristic(&[input("a"), generated("::b")]);
+    assert_eq!(output, "a::b");
+}
+
+#[test]
+fn heuristic_joint_punctuation() {
+    let output = heuristic(&[input("a"), generated("+="), input("  b")]);
+    assert_eq!(output, "a += b");
+
+    let output = heuristic(&[input("x"), generated("=> 'a")]);
+    assert_eq!(output, "x => 'a");
+}
+
+#[test]
+fn heuristic_lifetime() {
+    let output = heuristic(&[generated("&'a"), input("str")]);
+    assert_eq!(output, "&'a str");
+}
+
+#[test]
+fn heuristic_idents() {
+    let output = heuristic(&[generated("let"), input("x = 1")]);
+    assert_eq!(output, "let x = 1");
+
+    let output = heuristic(&[input("x"), generated("y"), input(" z")]);
+    assert_eq!(output, "x y z");
+}
+
+#[test]
+fn heuristic_leading_synthetic_token() {
+    let stream: TokenStream = "\n\n  (a  b\n   c . d)".parse().unwrap();
+    let Some(TokenTree::Group(group)) = stream.into_iter().next() else {
+        panic!("not a group");
+    };
+
+    // a generated token borrowing the span of the group, as `quote!` does with a
+    // `#generated #user_input` invocation
+    let generated = Ident::new("generated", group.span());
+    let output = heuristic(&[vec![generated.into()], group.stream().into_iter().collect()]);
+    assert_eq!(output, "generated a  b\n   c . d");
+}
+
+/// Tokens of `source`, in the `order` of their indices.
+fn reordered(source: &str, order: &[usize]) -> TokenStream {
+    let tokens = input(source);
+    order.iter().map(|&i| tokens[i].clone()).collect()
+}
+
+/// Display `stream` with `fallback`.
+fn with_fallback(stream: &TokenStream, fallback: SpanFallback) -> String {
+    FaithfulOptions::new().fallback(fallback).string(stream)
+}
+
+#[test]
+fn backwards_on_the_same_line() {
+    let stream = reordered("a b c", &[0, 2, 1]);
+
+    assert_eq!(with_fallback(&stream, SpanFallback::Space), "a   c b");
+    assert_eq!(with_fallback(&stream, SpanFallback::Nothing), "a   cb");
+
+    let error = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(&stream)
+        .unwrap_err();
+    assert!(matches!(error, FaithfulError::BackwardsSpan(_)));
+    assert_eq!(error.span().unwrap().region().start, LineColumn::new(1, 2));
+}
+
+#[test]
+fn backwards_across_lines() {
+    let stream = reordered("a\n    b\n  c d", &[0, 2, 1, 3]);
+
+    assert_eq!(with_fallback(&stream, SpanFallback::Space), "a\n\n  c b d");
+    assert_eq!(with_fallback(&stream, SpanFallback::Nothing), "a\n\n  cb d");
+
+    let output = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(&stream);
+    assert!(matches!(output, Err(FaithfulError::BackwardsSpan(_))));
+}
+
+#[test]
+fn overlapping_token() {
+    let tokens = input("ab + c d");
+
+    // a token overlapping the previous ones
+    let span = tokens[1].span().join(tokens[2].span()).unwrap();
+    let overlapping = Ident::new("x", span);
+    let stream: TokenStream = [&tokens[..3], &[overlapping.into(), tokens[3].clone()]]
+        .concat()
+        .into_iter()
+        .collect();
+
+    assert_eq!(with_fallback(&stream, SpanFallback::Space), "ab + c x d");
+    assert_eq!(with_fallback(&stream, SpanFallback::Nothing), "ab + cx d");
+}
+
+#[test]
+fn position_is_restored_after_backwards_group() {
+    let stream = reordered("f  (a)\n  g  h", &[0, 2, 1, 3]);
+
+    // `h` is laid out relatively to `g`, the last token in order
+    assert_eq!(with_fallback(&stream, SpanFallback::Space), "f\n  g (a)  h");
+    assert_eq!(
+        with_fallback(&stream, SpanFallback::Nothing),
+        "f\n  g(a)  h"
+    );
+
+    let error = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(&stream)
+        .unwrap_err();
+    assert_eq!(error.span().unwrap().region().start, LineColumn::new(1, 3));
+}
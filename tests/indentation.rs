@@ -0,0 +1,95 @@
+//! Dedenting and re-indenting of the lines of a rendering.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::TokenStream;
+use proc_macro_faithful_display::strategy::Pretty;
+use proc_macro_faithful_display::{FaithfulOptions, FirstLine};
+
+#[test]
+fn unanchored_first_line_is_not_reindented() {
+    let stream: TokenStream = "a\n        b c\n    d".parse().unwrap();
+
+    let output = FaithfulOptions::new().reindent(8).string(&stream);
+    assert_eq!(output, "a\n                b c\n            d");
+
+    let output = FaithfulOptions::new()
+        .reindent(8)
+        .first_line(FirstLine::Anchored)
+        .string(&stream);
+    assert_eq!(output, "        a\n                b c\n            d");
+}
+
+#[test]
+fn dedent_first_line_not_at_smallest_column() {
+    let stream: TokenStream = "    a x\n  b\n      c".parse().unwrap();
+
+    let output = FaithfulOptions::new().dedent(true).string(&stream);
+    assert_eq!(output, "a x\nb\n    c");
+
+    // the first line keeps its indentation relative to the other ones
+    let output = FaithfulOptions::new()
+        .dedent(true)
+        .first_line(FirstLine::Anchored)
+        .string(&stream);
+    assert_eq!(output, "  a x\nb\n    c");
+}
+
+#[test]
+fn reindent_first_line_not_at_smallest_column() {
+    let stream: TokenStream = "    a x\n  b\n      c".parse().unwrap();
+
+    let output = FaithfulOptions::new().reindent(4).string(&stream);
+    assert_eq!(output, "a x\n    b\n        c");
+
+    // the first line moves along with the other ones
+    let output = FaithfulOptions::new()
+        .reindent(4)
+        .first_line(FirstLine::Anchored)
+        .string(&stream);
+    assert_eq!(output, "      a x\n    b\n        c");
+}
+
+#[test]
+fn dedent_with_layout_strategy() {
+    let stream: TokenStream = "            fn f() { let x = 1; if x { y(); } }"
+        .parse()
+        .unwrap();
+
+    // the indentation chosen by the strategy is kept
+    let output = FaithfulOptions::new()
+        .layout(Pretty::default())
+        .dedent(true)
+        .string(&stream);
+    assert_eq!(
+        output,
+        "fn f() {\n    let x = 1;\n    if x {\n        y();\n    }\n}"
+    );
+}
+
+#[test]
+fn reindent_with_layout_strategy() {
+    let stream: TokenStream = "            fn f() { let x = 1; if x { y(); } }"
+        .parse()
+        .unwrap();
+
+    // nested levels keep their indentation relative to each other
+    let output = FaithfulOptions::new()
+        .layout(Pretty::default())
+        .reindent(2)
+        .string(&stream);
+    assert_eq!(
+        output,
+        "fn f() {\n      let x = 1;\n      if x {\n          y();\n      }\n  }"
+    );
+
+    let output = FaithfulOptions::new()
+        .layout(Pretty::default())
+        .reindent(2)
+        .first_line(FirstLine::Anchored)
+        .string(&stream);
+    assert_eq!(
+        output,
+        "  fn f() {\n      let x = 1;\n      if x {\n          y();\n      }\n  }"
+    );
+}
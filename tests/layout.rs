@@ -0,0 +1,61 @@
+//! Flattened layouts, rendered without the token stream they were computed from.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::TokenStream;
+use proc_macro_faithful_display::strategy::{Lexeme, Pretty};
+use proc_macro_faithful_display::{
+    faithful_to_string, FaithfulLayout, FaithfulOptions, LineColumn,
+};
+
+const SOURCE: &str = "/// Doc.\nfn f() {\n    g(1, [2]) . h\n}";
+
+#[test]
+fn layout_displays_as_the_stream() {
+    let stream: TokenStream = SOURCE.parse().unwrap();
+    let layout = FaithfulLayout::compute(&stream);
+
+    assert_eq!(layout.to_string(), faithful_to_string(&stream));
+
+    for options in [
+        FaithfulOptions::new().reindent(2),
+        FaithfulOptions::new().layout(Pretty::default()),
+    ] {
+        let displayed = layout.display_with(options.clone()).to_string();
+        assert_eq!(displayed, options.string(&stream));
+    }
+}
+
+#[test]
+fn layout_entries() {
+    let stream: TokenStream = SOURCE.parse().unwrap();
+    let layout = FaithfulLayout::compute(&stream);
+    let entries = layout.entries();
+
+    assert_eq!(entries.len(), 17);
+    assert_eq!(entries[0].text, "/// Doc.");
+    assert_eq!(entries[0].kind, Lexeme::Comment { line: true });
+
+    let two = &entries[11];
+    assert_eq!(two.text, "2");
+    assert_eq!(
+        two.start,
+        LineColumn {
+            line: 3,
+            column: 10
+        }
+    );
+    assert_eq!(two.depth, 3);
+
+    let pos = LineColumn { line: 3, column: 4 };
+    assert_eq!(layout.entry_at(pos).unwrap().text, "g");
+}
+
+#[test]
+fn sliced_layout() {
+    let stream: TokenStream = SOURCE.parse().unwrap();
+    let layout = FaithfulLayout::compute(&stream);
+
+    assert_eq!(layout.lines(3..4).to_string(), "g(1, [2]) . h");
+    assert_eq!(layout.slice(1..4).to_string(), "fn f(");
+}
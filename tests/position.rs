@@ -0,0 +1,102 @@
+//! Owned positions and regions, and layouts built out of them without any backend.
+
+use proc_macro_faithful_display::strategy::Lexeme;
+use proc_macro_faithful_display::token::Spacing;
+use proc_macro_faithful_display::{
+    Distance, FaithfulLayout, FaithfulOptions, LayoutEntry, Position, Region,
+};
+
+fn entry(text: &str, start: (usize, usize), kind: Lexeme) -> LayoutEntry {
+    let start = Position::new(start.0, start.1);
+    let end = Position::new(start.line, start.column + text.chars().count());
+
+    LayoutEntry {
+        text: text.to_owned(),
+        start,
+        end,
+        kind,
+        depth: 0,
+    }
+}
+
+fn punct(ch: char) -> Lexeme {
+    Lexeme::Punct {
+        ch,
+        spacing: Spacing::Alone,
+        joined: false,
+    }
+}
+
+#[test]
+fn distance() {
+    let from = Position::new(2, 8);
+
+    let same_line = from.distance_to(Position::new(2, 10));
+    assert_eq!(
+        same_line,
+        Some(Distance {
+            lines: 0,
+            columns: 2
+        })
+    );
+
+    let next_lines = from.distance_to(Position::new(4, 3));
+    assert_eq!(
+        next_lines,
+        Some(Distance {
+            lines: 2,
+            columns: 3
+        })
+    );
+
+    assert_eq!(from.distance_to(Position::new(2, 7)), None);
+}
+
+#[test]
+fn regions() {
+    let outer = Region::new(Position::new(1, 4), Position::new(3, 1));
+    let inner = Region::new(Position::new(2, 0), Position::new(2, 5));
+    let after = Region::new(Position::new(3, 1), Position::new(3, 2));
+
+    assert!(outer.contains(Position::new(1, 4)));
+    assert!(!outer.contains(Position::new(3, 1)));
+    assert!(outer.contains_region(inner));
+    assert!(!outer.overlaps(after));
+    assert_eq!(outer.cover(after).end, Position::new(3, 2));
+    assert_eq!(
+        inner.distance_to(after),
+        Some(Distance {
+            lines: 1,
+            columns: 1
+        })
+    );
+    assert!(inner < after);
+}
+
+#[test]
+fn layout_of_owned_entries() {
+    let layout: FaithfulLayout = vec![
+        entry("x", (1, 4), Lexeme::Ident),
+        entry("=", (1, 6), punct('=')),
+        entry("1", (2, 8), Lexeme::Literal),
+        entry(";", (2, 9), punct(';')),
+    ]
+    .into();
+
+    assert_eq!(layout.to_string(), "x =\n        1;");
+    assert_eq!(
+        layout
+            .display_with(FaithfulOptions::new().dedent(true))
+            .to_string(),
+        "x =\n    1;"
+    );
+
+    let region = layout.region().unwrap();
+    assert_eq!(
+        region,
+        Region::new(Position::new(1, 4), Position::new(2, 10))
+    );
+
+    let second_line = Region::new(Position::new(2, 0), Position::new(3, 0));
+    assert_eq!(layout.within(second_line).to_string(), "1;");
+}
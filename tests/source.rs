@@ -0,0 +1,146 @@
+//! Comments and spellings, read back from the source text of the tokens.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Ident, Literal, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::{faithful_to_string, FaithfulOptions};
+
+/// Display `stream` with the comments of the source.
+fn with_comments(stream: &TokenStream) -> String {
+    FaithfulOptions::new().comments(true).string(stream)
+}
+
+#[test]
+fn line_comments() {
+    let source = "let a = 1; // one\n// two\n  let b = 2;";
+    let stream: TokenStream = source.parse().unwrap();
+
+    assert_eq!(with_comments(&stream), source);
+    assert_eq!(faithful_to_string(&stream), "let a = 1;\n\n  let b = 2;");
+}
+
+#[test]
+fn block_comments() {
+    let source = "f(/* a */ x, /* multi\n  line */ y)";
+    let stream: TokenStream = source.parse().unwrap();
+
+    assert_eq!(with_comments(&stream), source);
+    assert_eq!(faithful_to_string(&stream), "f(        x,\n          y)");
+}
+
+#[test]
+fn nested_block_comments() {
+    let source = "a /* outer /* inner */ still outer */ b /**/ c /* // not a line comment */ d";
+    let stream: TokenStream = source.parse().unwrap();
+
+    assert_eq!(with_comments(&stream), source);
+}
+
+#[test]
+fn text_between_reordered_tokens() {
+    let tokens: Vec<TokenTree> = "a /* x */ b c"
+        .parse::<TokenStream>()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let stream: TokenStream = [tokens[0].clone(), tokens[2].clone(), tokens[1].clone()]
+        .into_iter()
+        .collect();
+
+    // the text between `a` and `c` contains `b`, so whitespaces are emitted instead; `b` is not
+    // in order, so the text before it is not looked for
+    assert_eq!(with_comments(&stream), "a           c b");
+}
+
+#[test]
+fn text_between_unrelated_tokens() {
+    let a: TokenStream = "a /* x */".parse().unwrap();
+    let b: TokenStream = "          b".parse().unwrap();
+    let stream: TokenStream = a.into_iter().chain(b).collect();
+
+    // the tokens come from different sources, which text cannot be joined
+    assert_eq!(with_comments(&stream), "a         b");
+}
+
+/// Display `stream` with the spellings of the source.
+fn with_spelling(stream: &TokenStream) -> String {
+    FaithfulOptions::new().source_spelling(true).string(stream)
+}
+
+/// Replace the last token of `source` by `token`, keeping the span of the replaced token.
+fn respelled(source: &str, mut token: TokenTree) -> TokenStream {
+    let mut tokens: Vec<TokenTree> = source.parse::<TokenStream>().unwrap().into_iter().collect();
+    let last = tokens.pop().unwrap();
+    token.set_span(last.span());
+    tokens.push(token);
+    tokens.into_iter().collect()
+}
+
+#[test]
+fn spelling_of_escaped_literals() {
+    let stream = respelled("x = \"a\\x41\\u{42}\"", Literal::string("aAB").into());
+
+    assert_eq!(with_spelling(&stream), "x = \"a\\x41\\u{42}\"");
+    assert_eq!(faithful_to_string(&stream), "x = \"aAB\"");
+}
+
+#[test]
+fn spelling_of_raw_strings() {
+    let raw: Literal = "r#\"q\"#".parse().unwrap();
+    let stream = respelled("x = r##\"q\"##", raw.into());
+
+    assert_eq!(with_spelling(&stream), "x = r##\"q\"##");
+    assert_eq!(faithful_to_string(&stream), "x = r#\"q\"#");
+}
+
+#[test]
+fn spelling_of_mismatched_spans() {
+    // the source text doesn’t look like the token
+    let stream = respelled("x = r\"q\"", Literal::string("q").into());
+    assert_eq!(with_spelling(&stream), "x = \"q\"");
+
+    let stream = respelled("x = foo", Literal::string("q").into());
+    assert_eq!(with_spelling(&stream), "x = \"q\"");
+
+    let stream = respelled("x = \"q\"", Ident::new("foo", Span::call_site()).into());
+    assert_eq!(with_spelling(&stream), "x = foo");
+}
+
+#[test]
+fn spelling_of_call_site_tokens() {
+    let stream: TokenStream = [
+        TokenTree::from(Ident::new("x", Span::call_site())),
+        Literal::string("q").into(),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(with_spelling(&stream), "x \"q\"");
+}
+
+#[test]
+fn whitespaces_of_comment_gaps() {
+    // tabs and whitespaces of blank lines are kept as they are in the source
+    let source = "a\t// b\n \t\n\tc /* d */\td";
+    let stream: TokenStream = source.parse().unwrap();
+
+    assert_eq!(with_comments(&stream), source);
+}
+
+#[test]
+fn spelling_of_decomposed_identifiers() {
+    // `e` followed by a combining acute accent, which the compiler normalizes to `é`
+    let stream = respelled(
+        "x = cafe\u{301}",
+        Ident::new("café", Span::call_site()).into(),
+    );
+
+    assert_eq!(with_spelling(&stream), "x = cafe\u{301}");
+    assert_eq!(faithful_to_string(&stream), "x = café");
+
+    let stream = respelled(
+        "x = r#cafe\u{308}",
+        Ident::new_raw("cafë", Span::call_site()).into(),
+    );
+    assert_eq!(with_spelling(&stream), "x = r#cafe\u{308}");
+}
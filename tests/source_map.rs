@@ -0,0 +1,166 @@
+//! Source maps, from offsets of a rendering back to the spans of the tokens.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{TokenStream, TokenTree};
+use proc_macro_faithful_display::token::SourceSpan;
+use proc_macro_faithful_display::{FaithfulOptions, LineColumn, Mapping, Region, SourceMap};
+
+const SOURCE: &str = "let x = \"ab\ncd\";\nfoo(\n  y)";
+
+/// Display `SOURCE`, with its source map.
+fn rendered() -> (String, SourceMap<TokenTree>) {
+    let stream: TokenStream = SOURCE.parse().unwrap();
+    let (output, map) = FaithfulOptions::new().string_with_map(&stream);
+    assert_eq!(output, SOURCE);
+
+    (output, map)
+}
+
+/// Text of the tokens `mappings` are for.
+fn texts(output: &str, mappings: &[Mapping<TokenTree>]) -> Vec<String> {
+    mappings
+        .iter()
+        .map(|mapping| output[mapping.range.clone()].to_owned())
+        .collect()
+}
+
+/// Region of a span, from line `a` column `b` to line `c` column `d`.
+fn region(a: usize, b: usize, c: usize, d: usize) -> Region {
+    Region::new(LineColumn::new(a, b), LineColumn::new(c, d))
+}
+
+#[test]
+fn mapping_at_token_boundaries() {
+    let (output, map) = rendered();
+    let text_at = |offset| map.mapping_at(offset).map(|m| &output[m.range.clone()]);
+
+    assert_eq!(text_at(0), Some("let"));
+    assert_eq!(text_at(2), Some("let"));
+    assert_eq!(text_at(4), Some("x"));
+    assert_eq!(text_at(19), Some("foo"));
+    // `foo` ends where `(` starts
+    assert_eq!(text_at(20), Some("("));
+    assert_eq!(text_at(25), Some(")"));
+}
+
+#[test]
+fn mapping_at_gaps() {
+    let (output, map) = rendered();
+
+    for offset in [3, 5, 16, 21, 22, 23] {
+        assert!(map.mapping_at(offset).is_none(), "{offset}");
+    }
+
+    assert!(map.mapping_at(output.len()).is_none());
+    assert!(map.mapping_at(output.len() + 1).is_none());
+    assert!(map.span_at(output.len()).is_none());
+}
+
+#[test]
+fn mappings_for_ranges() {
+    let (output, map) = rendered();
+    let texts_for = |range| texts(&output, map.mappings_for_range(range));
+
+    assert_eq!(texts_for(2..5), ["let", "x"]);
+    assert_eq!(texts_for(3..4), [""; 0]);
+    assert_eq!(texts_for(0..output.len()).len(), map.mappings().len());
+    assert_eq!(texts_for(24..100), ["y", ")"]);
+}
+
+#[test]
+fn mappings_for_empty_ranges() {
+    let (output, map) = rendered();
+    let texts_for = |range| texts(&output, map.mappings_for_range(range));
+
+    // an empty range overlaps the token it’s in
+    assert_eq!(texts_for(4..4), ["x"]);
+    assert_eq!(texts_for(0..0), ["let"]);
+    assert_eq!(texts_for(3..3), [""; 0]);
+    assert_eq!(texts_for(output.len()..output.len()), [""; 0]);
+    assert!(map.span_for_range(3..3).is_none());
+}
+
+#[test]
+#[allow(clippy::reversed_empty_ranges)]
+fn mappings_for_reversed_ranges() {
+    let (output, map) = rendered();
+    let texts_for = |range| texts(&output, map.mappings_for_range(range));
+
+    // a reversed range is as empty as the range starting at its start
+    assert_eq!(texts_for(5..0), [""; 0]);
+    assert_eq!(texts_for(20..4), ["("]);
+    assert_eq!(texts_for(100..4), [""; 0]);
+
+    let narrowed = map.subspan_for_range(&output, 20..4).unwrap();
+    assert_eq!(narrowed.start, narrowed.end);
+}
+
+#[test]
+fn subspan_in_literal() {
+    let (output, map) = rendered();
+
+    // `b`, on the first line of the literal
+    let narrowed = map.subspan_at(&output, 10).unwrap();
+    assert!(narrowed.exact);
+    assert_eq!(narrowed.span.region(), region(1, 10, 1, 11));
+    assert_eq!(
+        (narrowed.start, narrowed.end),
+        (LineColumn::new(1, 10), LineColumn::new(1, 11))
+    );
+
+    // `d`, on the second line of the literal
+    let narrowed = map.subspan_at(&output, 13).unwrap();
+    assert!(narrowed.exact);
+    assert_eq!(narrowed.span.region(), region(2, 1, 2, 2));
+    assert_eq!(
+        (narrowed.start, narrowed.end),
+        (LineColumn::new(2, 1), LineColumn::new(2, 2))
+    );
+
+    // `b\nc`, across the newline
+    let narrowed = map.subspan_for_range(&output, 10..13).unwrap();
+    assert!(narrowed.exact);
+    assert_eq!(narrowed.span.region(), region(1, 10, 2, 1));
+}
+
+#[test]
+fn subspan_in_ident() {
+    let (output, map) = rendered();
+
+    // `oo`: the span of an identifier cannot be narrowed, but the positions are
+    let narrowed = map.subspan_for_range(&output, 18..20).unwrap();
+    assert!(!narrowed.exact);
+    assert_eq!(narrowed.span.region(), region(3, 0, 3, 3));
+    assert_eq!(
+        (narrowed.start, narrowed.end),
+        (LineColumn::new(3, 1), LineColumn::new(3, 3))
+    );
+}
+
+#[test]
+fn subspan_across_lines() {
+    let (output, map) = rendered();
+
+    // from `oo` to `y`, spanning several tokens and lines
+    let narrowed = map.subspan_for_range(&output, 18..25).unwrap();
+    assert!(!narrowed.exact);
+    assert_eq!(narrowed.span.region(), region(3, 0, 3, 3));
+    assert_eq!(
+        (narrowed.start, narrowed.end),
+        (LineColumn::new(3, 1), LineColumn::new(4, 3))
+    );
+
+    // ranges starting or ending in gaps are narrowed to the tokens they overlap
+    let narrowed = map.subspan_for_range(&output, 16..22).unwrap();
+    assert_eq!(
+        (narrowed.start, narrowed.end),
+        (LineColumn::new(3, 0), LineColumn::new(3, 4))
+    );
+
+    assert!(map.subspan_for_range(&output, 21..24).is_none());
+    assert!(map.subspan_at(&output, output.len()).is_none());
+
+    let span = map.span_at_line_column(&output, LineColumn::new(4, 2));
+    assert_eq!(span.unwrap().region(), region(4, 2, 4, 3));
+}
@@ -0,0 +1,218 @@
+//! Tokens of a custom type, laid out by hand, independently of any backend.
+
+use std::fmt;
+
+use proc_macro_faithful_display::token::{Delimiter, SourceSpan, Spacing, Token, TokenKind};
+use proc_macro_faithful_display::{
+    faithful_to_string, FaithfulDisplay, FaithfulError, FaithfulOptions, LineColumn, Region,
+    SpanFallback,
+};
+
+/// Span of a [`Tok`], from a start to an end position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Pos {
+    start: LineColumn,
+    end: LineColumn,
+}
+
+impl SourceSpan for Pos {
+    fn start(&self) -> LineColumn {
+        self.start
+    }
+
+    fn end(&self) -> LineColumn {
+        self.end
+    }
+}
+
+/// A token built by hand.
+#[derive(Clone, Debug)]
+enum Tok {
+    Leaf {
+        text: &'static str,
+        kind: TokenKind<Pos, Vec<Tok>>,
+        span: Pos,
+    },
+    Group {
+        delimiter: Delimiter,
+        open: Pos,
+        close: Pos,
+        stream: Vec<Tok>,
+    },
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tok::Leaf { text, .. } => f.write_str(text),
+            Tok::Group { .. } => unreachable!("groups are never displayed"),
+        }
+    }
+}
+
+impl Token for Tok {
+    type Span = Pos;
+    type Stream = Vec<Tok>;
+
+    fn span(&self) -> Pos {
+        match self {
+            Tok::Leaf { span, .. } => *span,
+            Tok::Group { open, close, .. } => Pos {
+                start: open.start,
+                end: close.end,
+            },
+        }
+    }
+
+    fn kind(&self) -> TokenKind<Pos, Vec<Tok>> {
+        match self {
+            Tok::Leaf { kind, .. } => kind.clone(),
+            Tok::Group {
+                delimiter,
+                open,
+                close,
+                stream,
+            } => TokenKind::Group {
+                delimiter: *delimiter,
+                open: *open,
+                close: *close,
+                stream: stream.clone(),
+            },
+        }
+    }
+}
+
+/// Span of `len` characters at line `line`, column `column`.
+fn at(line: usize, column: usize, len: usize) -> Pos {
+    Pos {
+        start: LineColumn::new(line, column),
+        end: LineColumn::new(line, column + len),
+    }
+}
+
+/// Identifier `text` at line `line`, column `column`.
+fn ident(text: &'static str, (line, column): (usize, usize)) -> Tok {
+    Tok::Leaf {
+        text,
+        kind: TokenKind::Ident,
+        span: at(line, column, text.len()),
+    }
+}
+
+/// Punctuation `ch`, alone, at line `line`, column `column`.
+fn punct(ch: &'static str, (line, column): (usize, usize)) -> Tok {
+    Tok::Leaf {
+        text: ch,
+        kind: TokenKind::Punct(ch.chars().next().unwrap(), Spacing::Alone),
+        span: at(line, column, 1),
+    }
+}
+
+/// Group delimited by `delimiter`, opening and closing at the given lines and columns.
+fn group(
+    delimiter: Delimiter,
+    (open_line, open_column): (usize, usize),
+    (close_line, close_column): (usize, usize),
+    stream: Vec<Tok>,
+) -> Tok {
+    Tok::Group {
+        delimiter,
+        open: at(open_line, open_column, 1),
+        close: at(close_line, close_column, 1),
+        stream,
+    }
+}
+
+/// `fn f() {\n    x;\n}`, at `line`.
+fn function(line: usize) -> Vec<Tok> {
+    vec![
+        ident("fn", (line, 0)),
+        ident("f", (line, 3)),
+        group(Delimiter::Parenthesis, (line, 4), (line, 5), vec![]),
+        group(
+            Delimiter::Brace,
+            (line, 7),
+            (line + 2, 0),
+            vec![ident("x", (line + 1, 4)), punct(";", (line + 1, 5))],
+        ),
+    ]
+}
+
+#[test]
+fn hand_laid_out_tokens() {
+    let tokens = function(3);
+    assert_eq!(faithful_to_string(tokens.as_slice()), "fn f() {\n    x;\n}");
+
+    assert_eq!(tokens.faithful_start(), Some(LineColumn::new(3, 0)));
+    assert_eq!(
+        tokens.faithful_region(),
+        Some(Region::new(LineColumn::new(3, 0), LineColumn::new(5, 1)))
+    );
+}
+
+#[test]
+fn invisible_groups() {
+    // `$e:expr` forwarded as an invisible group spanning the whole expression
+    let tokens = vec![
+        ident("let", (1, 2)),
+        ident("a", (1, 6)),
+        punct("=", (1, 8)),
+        group(
+            Delimiter::None,
+            (1, 10),
+            (1, 14),
+            vec![
+                ident("b", (1, 10)),
+                punct("+", (1, 12)),
+                ident("c", (1, 14)),
+            ],
+        ),
+        punct(";", (1, 15)),
+    ];
+
+    assert_eq!(faithful_to_string(tokens.as_slice()), "let a = b + c;");
+    assert_eq!(tokens.faithful_start(), Some(LineColumn::new(1, 2)));
+}
+
+#[test]
+fn tokens_out_of_order() {
+    // the second function is laid out before the first one
+    let mut tokens = function(3);
+    tokens.extend(function(1));
+
+    let output = FaithfulOptions::new()
+        .fallback(SpanFallback::Space)
+        .string(tokens.as_slice());
+    // every token before the position is separated from the last one; the tokens of a group are
+    // laid out relatively to its opening delimiter
+    assert_eq!(output, "fn f() {\n    x;\n} fn f () {\n    x;\n}");
+
+    let error = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(tokens.as_slice())
+        .unwrap_err();
+    assert!(matches!(error, FaithfulError::BackwardsSpan(_)));
+    assert_eq!(error.span(), Some(&at(1, 0, 2)));
+}
+
+#[test]
+fn wide_delimiter_spans() {
+    // a group which delimiters have the span of the whole group
+    let span = Pos {
+        start: LineColumn::new(1, 0),
+        end: LineColumn::new(1, 5),
+    };
+    let tokens = vec![Tok::Group {
+        delimiter: Delimiter::Bracket,
+        open: span,
+        close: span,
+        stream: vec![ident("a", (1, 1)), punct(",", (1, 2)), ident("b", (1, 4))],
+    }];
+
+    let error = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .try_string(tokens.as_slice())
+        .unwrap_err();
+    assert!(matches!(error, FaithfulError::MissingDelimiterSpan(_)));
+    assert_eq!(error.span(), Some(&span));
+}
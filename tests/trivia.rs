@@ -0,0 +1,92 @@
+//! Tokens annotated with the whitespaces preceding them.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::{tokens_with_trivia, Trivia, TriviaTokens};
+
+/// Text of a token, groups being reduced to their delimiters.
+fn text(token: &TokenTree) -> String {
+    match token {
+        TokenTree::Group(group) => match group.delimiter() {
+            Delimiter::Parenthesis => "()".to_owned(),
+            Delimiter::Brace => "{}".to_owned(),
+            Delimiter::Bracket => "[]".to_owned(),
+            Delimiter::None => String::new(),
+        },
+        _ => token.to_string(),
+    }
+}
+
+/// Text and trivia – newlines, spaces and adjacency – of the tokens of an iterator.
+fn annotated(
+    tokens: impl Iterator<Item = (TokenTree, Trivia)>,
+) -> Vec<(String, usize, usize, bool)> {
+    tokens
+        .map(|(token, trivia)| {
+            let text = text(&token);
+            (text, trivia.newlines, trivia.spaces, trivia.adjacent)
+        })
+        .collect()
+}
+
+#[test]
+fn adjacency() {
+    let stream: TokenStream = "a.b a . b".parse().unwrap();
+    let tokens = annotated(tokens_with_trivia(&stream));
+
+    assert_eq!(
+        tokens,
+        [
+            ("a".to_owned(), 0, 0, false),
+            (".".to_owned(), 0, 0, true),
+            ("b".to_owned(), 0, 0, true),
+            ("a".to_owned(), 0, 1, false),
+            (".".to_owned(), 0, 1, false),
+            ("b".to_owned(), 0, 1, false),
+        ]
+    );
+}
+
+#[test]
+fn newlines() {
+    let stream: TokenStream = "x = 1;\n\n    y (2)".parse().unwrap();
+    let tokens = annotated(tokens_with_trivia(&stream));
+
+    assert_eq!(tokens[4], ("y".to_owned(), 2, 4, false));
+    assert_eq!(tokens[5], ("()".to_owned(), 0, 1, false));
+}
+
+#[test]
+fn inside_groups() {
+    let stream: TokenStream = "f( a,b\n)".parse().unwrap();
+    let group = stream.into_iter().nth(1).unwrap();
+
+    let mut inside = TriviaTokens::inside(&group).unwrap();
+    let tokens = annotated(inside.by_ref());
+    assert_eq!(
+        tokens,
+        [
+            ("a".to_owned(), 0, 1, false),
+            (",".to_owned(), 0, 0, true),
+            ("b".to_owned(), 0, 0, true),
+        ]
+    );
+
+    let trailing = inside.trailing().unwrap();
+    assert_eq!((trailing.newlines, trailing.spaces), (1, 0));
+}
+
+#[test]
+fn invisible_groups_are_transparent() {
+    let stream: TokenStream = "a + b".parse().unwrap();
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+
+    let mut group = Group::new(Delimiter::None, tokens[1..].iter().cloned().collect());
+    group.set_span(Span::call_site());
+    let stream: TokenStream = [tokens[0].clone(), group.into()].into_iter().collect();
+
+    let tokens = annotated(tokens_with_trivia(&stream));
+    assert_eq!(tokens[1], ("+".to_owned(), 0, 1, false));
+    assert_eq!(tokens[2], ("b".to_owned(), 0, 1, false));
+}
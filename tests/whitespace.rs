@@ -0,0 +1,77 @@
+//! Whitespace options, applied to the whitespaces emitted between tokens.
+
+#![cfg(feature = "proc-macro2")]
+
+use proc_macro2::TokenStream;
+use proc_macro_faithful_display::{FaithfulOptions, Indent, Newline};
+
+#[test]
+fn crlf_newlines() {
+    let stream: TokenStream = "a\n\n  b \"c\nd\"\n// e\nf".parse().unwrap();
+    let options = FaithfulOptions::new().newline(Newline::CrLf);
+
+    // the content of tokens is left untouched, and the line of the comment is left blank
+    assert_eq!(options.string(&stream), "a\r\n\r\n  b \"c\nd\"\r\n\r\nf");
+
+    // gaps read from the source are written with the chosen style as well
+    let crlf: TokenStream = "a // b\r\n\r\n  c".parse().unwrap();
+    let output = options.comments(true).string(&crlf);
+    assert_eq!(output, "a // b\r\n\r\n  c");
+}
+
+#[test]
+fn tab_indentation() {
+    let stream: TokenStream = "fn f() {\n        x\n      y\n}".parse().unwrap();
+
+    let output = FaithfulOptions::new()
+        .indent(Indent::Tabs { width: 4 })
+        .string(&stream);
+    assert_eq!(output, "fn f() {\n\t\tx\n\t  y\n}");
+
+    // tabs of the input count as single columns
+    let tabs: TokenStream = "{\n\t\tx\n\ty\n}".parse().unwrap();
+    let output = FaithfulOptions::new()
+        .indent(Indent::Tabs { width: 1 })
+        .string(&tabs);
+    assert_eq!(output, "{\n\t\tx\n\ty\n}");
+}
+
+#[test]
+fn max_blank_lines() {
+    let stream: TokenStream = "a\n\n\n\n  b\n\nc".parse().unwrap();
+
+    let output = FaithfulOptions::new().max_blank_lines(1).string(&stream);
+    assert_eq!(output, "a\n\n  b\n\nc");
+
+    let output = FaithfulOptions::new().max_blank_lines(0).string(&stream);
+    assert_eq!(output, "a\n  b\nc");
+}
+
+#[test]
+fn trim_trailing_whitespace() {
+    let stream: TokenStream = "a   \n  // b  \n   \nc \"d  \n\"".parse().unwrap();
+    let options = FaithfulOptions::new().comments(true);
+
+    assert_eq!(options.string(&stream), "a   \n  // b  \n   \nc \"d  \n\"");
+
+    // the content of tokens is left untouched
+    let output = options.trim_trailing_whitespace(true).string(&stream);
+    assert_eq!(output, "a\n  // b\n\nc \"d  \n\"");
+}
+
+#[test]
+fn final_newline() {
+    let stream: TokenStream = "a\n  b".parse().unwrap();
+    let options = FaithfulOptions::new().final_newline(true);
+
+    assert_eq!(options.string(&stream), "a\n  b\n");
+    assert_eq!(
+        options.clone().newline(Newline::CrLf).string(&stream),
+        "a\r\n  b\r\n"
+    );
+    assert_eq!(options.string(&TokenStream::new()), "");
+
+    // the output already ends with a newline
+    let comment: TokenStream = "a // b\n".parse().unwrap();
+    assert_eq!(options.string(&comment), "a\n");
+}
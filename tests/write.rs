@@ -0,0 +1,142 @@
+//! Rendering into writers and strings, without going through `Display`.
+
+#![cfg(feature = "proc-macro2")]
+
+use std::io;
+
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use proc_macro_faithful_display::{
+    faithful_display, faithful_display_owned, faithful_to_string, faithful_write_io,
+    into_faithful_string, FaithfulOptions, FirstLine, SpanFallback,
+};
+
+/// Writer failing once `capacity` bytes were written.
+struct Failing {
+    written: Vec<u8>,
+    capacity: usize,
+}
+
+impl io::Write for Failing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.capacity - self.written.len());
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"));
+        }
+
+        self.written.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn io_errors_are_passed_through() {
+    let stream: TokenStream = "fn f() {\n    x\n}".parse().unwrap();
+    let mut out = Failing {
+        written: Vec::new(),
+        capacity: 6,
+    };
+
+    let err = faithful_write_io(&stream, &mut out).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(err.to_string(), "pipe closed");
+    assert_eq!(out.written, b"fn f()");
+}
+
+#[test]
+fn rendering_errors_are_invalid_data() {
+    let tokens: Vec<TokenTree> = "a b".parse::<TokenStream>().unwrap().into_iter().collect();
+    let stream: TokenStream = [tokens[1].clone(), tokens[0].clone()].into_iter().collect();
+    let mut out = Vec::new();
+
+    let err = FaithfulOptions::new()
+        .fallback(SpanFallback::Strict)
+        .write_io(&stream, &mut out)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn to_string_is_display() {
+    for source in [
+        "",
+        "a",
+        "  fn f() {\n        x\n    }",
+        "/// Doc.\nstruct S;",
+        "f(\"a\nb\", 'c', r#\"d\"#)",
+    ] {
+        let stream: TokenStream = source.parse().unwrap();
+        let output = faithful_to_string(&stream);
+
+        assert_eq!(output, faithful_display(&stream).to_string(), "{source:?}");
+
+        let mut out = Vec::new();
+        faithful_write_io(&stream, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), output, "{source:?}");
+    }
+}
+
+#[test]
+fn strings_are_allocated_up_front() {
+    let source =
+        "fn main() {\n    let v = vec4(1.0, 0.5, 0.25, 1.0);\n    if v.x > 0.5 {\n        \
+                  for i in 0..4 {\n            out[i] = v * 2.0;\n        }\n    }\n}";
+    let stream: TokenStream = source.parse().unwrap();
+
+    // the extent of ASCII tokens laid out faithfully is exact, so the string is never reallocated
+    let output = faithful_to_string(&stream);
+    assert_eq!(output, source);
+    assert_eq!(output.capacity(), output.len());
+
+    let output = FaithfulOptions::new().try_string(&stream).unwrap();
+    assert_eq!(output.capacity(), output.len());
+}
+
+/// A nested stream, mixing input tokens and generated ones.
+fn nested() -> TokenStream {
+    let input: TokenStream = "  fn f() {\n      g(\n        [1, 2]);\n  }"
+        .parse()
+        .unwrap();
+    let generated = ["struct", "S"]
+        .into_iter()
+        .map(|name| TokenTree::from(Ident::new(name, Span::call_site())));
+
+    input.into_iter().chain(generated).collect()
+}
+
+#[test]
+fn owned_streams_are_displayed_as_borrowed_ones() {
+    let stream = nested();
+
+    let output = faithful_to_string(&stream);
+    assert_eq!(output, "fn f() {\n      g(\n        [1, 2]);\n  } struct S");
+    assert_eq!(into_faithful_string(stream.clone()), output);
+    assert_eq!(faithful_display_owned(stream.clone()).to_string(), output);
+
+    for options in [
+        FaithfulOptions::new().dedent(true),
+        FaithfulOptions::new()
+            .reindent(4)
+            .first_line(FirstLine::Anchored),
+        FaithfulOptions::new().fallback(SpanFallback::Heuristic),
+        FaithfulOptions::new().fallback(SpanFallback::Nothing),
+    ] {
+        let output = options.string(&stream);
+
+        assert_eq!(options.into_string(stream.clone()), output, "{options:?}");
+        assert_eq!(
+            options.display_owned(stream.clone()).to_string(),
+            output,
+            "{options:?}"
+        );
+    }
+
+    // rendering errors are ignored, the output being as complete as possible
+    let options = FaithfulOptions::new().fallback(SpanFallback::Strict);
+    let output = options.into_string(stream.clone());
+    assert_eq!(output, options.string(&stream));
+    assert_eq!(output, "fn f() {\n      g(\n        [1, 2]);\n  }");
+}